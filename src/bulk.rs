@@ -0,0 +1,145 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    io::{self, BufReader},
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use scryfall::Card;
+use serde::{
+    de::{SeqAccess, Visitor},
+    Deserialize, Deserializer, Serialize,
+};
+use tokio::sync::OnceCell;
+use uuid::Uuid;
+
+use crate::{
+    cache::cache_dir,
+    card_name::{CName, CardName},
+    checklist::Set,
+    persist,
+};
+
+fn index_file() -> PathBuf {
+    cache_dir().join("bulk.json")
+}
+
+/// What xander needs from a scryfall `default-cards` bulk file.
+#[derive(Serialize, Deserialize)]
+pub struct Index {
+    pub built_at: DateTime<Utc>,
+    /// One printing per card, keyed by the name of its front face.
+    pub cards: HashMap<CardName, Card>,
+    /// Every printing of a card, keyed by oracle id.
+    pub printings: HashMap<Uuid, Vec<Set>>,
+}
+
+impl Index {
+    pub fn card(&self, name: &CName) -> Option<&Card> {
+        self.cards.get(name)
+    }
+
+    pub fn printings(&self, card: &Card) -> Option<&[Set]> {
+        self.printings.get(&card.oracle_id?).map(Vec::as_slice)
+    }
+
+    fn add(&mut self, card: Card) {
+        if let Some(oracle_id) = card.oracle_id {
+            self.printings
+                .entry(oracle_id)
+                .or_default()
+                .push(Set::of_printing(&card));
+        }
+        if card.lang != "en" {
+            return;
+        }
+        let name = CardName::from(
+            match card.card_faces.as_ref().and_then(|faces| faces.first()) {
+                Some(front_face) => front_face.name.clone(),
+                None => card.name.clone(),
+            },
+        );
+        // the newest paper printing, like scryfall's own name lookup
+        let rank = |card: &Card| (!card.digital, card.released_at);
+        match self.cards.get_mut(name.as_slice()) {
+            Some(existing) if rank(existing) >= rank(&card) => {}
+            Some(existing) => *existing = card,
+            None => {
+                self.cards.insert(name, card);
+            }
+        }
+    }
+}
+
+/// Calls `f` on every card of the bulk file without holding the whole file in memory. Cards
+/// this version of xander can't parse are skipped and counted.
+fn for_each_card<R: io::Read>(reader: R, f: impl FnMut(Card)) -> anyhow::Result<usize> {
+    struct Cards<F>(F, usize);
+
+    impl<'de, F: FnMut(Card)> Visitor<'de> for &mut Cards<F> {
+        type Value = ();
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            formatter.write_str("an array of cards")
+        }
+
+        fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<(), A::Error> {
+            while let Some(card) = seq.next_element::<serde_json::Value>()? {
+                match serde_json::from_value(card) {
+                    Ok(card) => (self.0)(card),
+                    Err(_) => self.1 += 1,
+                }
+            }
+            Ok(())
+        }
+    }
+
+    let mut cards = Cards(f, 0);
+    serde_json::Deserializer::from_reader(reader).deserialize_seq(&mut cards)?;
+    Ok(cards.1)
+}
+
+/// Builds the index from a downloaded `default-cards` file, replacing the previous one.
+pub async fn ingest(bulk_file: &Path) -> anyhow::Result<Index> {
+    let bulk_file = bulk_file.to_owned();
+    let index = tokio::task::spawn_blocking(move || {
+        let file = std::fs::File::open(&bulk_file)
+            .with_context(|| format!("opening {}", bulk_file.display()))?;
+        let mut index = Index {
+            built_at: Utc::now(),
+            cards: HashMap::new(),
+            printings: HashMap::new(),
+        };
+        let skipped = for_each_card(BufReader::new(file), |card| index.add(card))
+            .with_context(|| format!("reading {}", bulk_file.display()))?;
+        if skipped > 0 {
+            println!("[WARN] skipped {skipped} cards that couldn't be parsed");
+        }
+        anyhow::Ok(index)
+    })
+    .await??;
+    tokio::fs::create_dir_all(cache_dir()).await?;
+    persist::atomic_write(&index_file(), &serde_json::to_vec(&index)?).await?;
+    Ok(index)
+}
+
+/// The ingested bulk data, if there is any.
+pub async fn index() -> anyhow::Result<Option<&'static Index>> {
+    static INDEX: OnceCell<Option<Index>> = OnceCell::const_new();
+    let index = INDEX
+        .get_or_try_init(|| async {
+            let index = match tokio::fs::read(index_file()).await {
+                Ok(index) => index,
+                Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+                Err(e) => return Err(anyhow::Error::from(e)),
+            };
+            let index = tokio::task::spawn_blocking(move || serde_json::from_slice(&index))
+                .await?
+                .context("reading the bulk data index, run the bulk command again")?;
+            Ok(Some(index))
+        })
+        .await?;
+    Ok(index.as_ref())
+}
@@ -0,0 +1,295 @@
+use std::{
+    borrow::Borrow,
+    collections::HashMap,
+    fmt,
+    future::Future,
+    hash::Hash,
+    io,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        OnceLock,
+    },
+};
+
+use anyhow::bail;
+use chrono::{DateTime, TimeDelta, Utc};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use tokio::sync::{OnceCell, RwLock, Semaphore};
+
+use crate::{persist, PROG_NAME};
+
+/// The names of every cache, for the `cache` command.
+pub const CACHES: [&str; 3] = ["staples", "printings", "staples-lists"];
+
+pub fn default_dir() -> PathBuf {
+    let mut cache_dir = dirs::cache_dir().unwrap();
+    cache_dir.push(PROG_NAME);
+    cache_dir
+}
+
+static CACHE_DIR: OnceLock<PathBuf> = OnceLock::new();
+
+/// Moves the caches for the rest of the run, must be called before any cache is used.
+pub fn set_dir(dir: PathBuf) {
+    let _ = CACHE_DIR.set(dir);
+}
+
+pub fn cache_dir() -> &'static PathBuf {
+    CACHE_DIR.get_or_init(default_dir)
+}
+
+static CONCURRENCY: OnceLock<usize> = OnceLock::new();
+
+/// Sets how many requests are made at the same time, must be called before any cache is used.
+pub fn set_concurrency(concurrency: usize) {
+    let _ = CONCURRENCY.set(concurrency);
+}
+
+pub fn concurrency() -> usize {
+    *CONCURRENCY.get_or_init(|| 8)
+}
+
+static QUIET: AtomicBool = AtomicBool::new(false);
+
+/// Stops the caches from printing what they download, for while the ui owns the terminal.
+pub fn set_quiet(quiet: bool) {
+    QUIET.store(quiet, Ordering::Relaxed);
+}
+
+pub fn quiet() -> bool {
+    QUIET.load(Ordering::Relaxed)
+}
+
+fn cache_file(name: &str) -> PathBuf {
+    cache_dir().join(format!("{name}.json"))
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Policy {
+    pub ttl: TimeDelta,
+    /// Entries fetched before this are considered stale regardless of their age.
+    pub refresh_before: Option<DateTime<Utc>>,
+    /// Never download anything, every cached entry is used however old it is.
+    pub offline: bool,
+}
+
+impl Default for Policy {
+    fn default() -> Self {
+        Self {
+            ttl: TimeDelta::days(30),
+            refresh_before: None,
+            offline: false,
+        }
+    }
+}
+
+impl Policy {
+    fn is_fresh(&self, fetched_at: DateTime<Utc>) -> bool {
+        self.offline
+            || (self
+                .refresh_before
+                .is_none_or(|before| fetched_at >= before)
+                && Utc::now() - fetched_at < self.ttl)
+    }
+}
+
+static POLICY: OnceLock<Policy> = OnceLock::new();
+
+/// Sets the expiry policy for the rest of the run, must be called before any cache is used.
+pub fn set_policy(ttl_days: u32, refresh: bool, offline: bool) {
+    let _ = POLICY.set(Policy {
+        ttl: TimeDelta::days(ttl_days.into()),
+        refresh_before: refresh.then(Utc::now),
+        offline,
+    });
+}
+
+pub fn policy() -> &'static Policy {
+    POLICY.get_or_init(Policy::default)
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Entry<V> {
+    fetched_at: DateTime<Utc>,
+    value: V,
+}
+
+async fn read<K, V>(name: &str) -> anyhow::Result<HashMap<K, Entry<V>>>
+where
+    K: Eq + Hash + DeserializeOwned,
+    V: DeserializeOwned,
+{
+    let bytes = match tokio::fs::read(cache_file(name)).await {
+        Ok(bytes) => bytes,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(HashMap::new()),
+        Err(e) => return Err(e.into()),
+    };
+    match serde_json::from_slice(&bytes) {
+        Ok(entries) => Ok(entries),
+        Err(e) => {
+            // caches written before entries were timestamped, they are all considered stale
+            let Ok(legacy) = serde_json::from_slice::<HashMap<K, V>>(&bytes) else {
+                return Err(e.into());
+            };
+            Ok(legacy
+                .into_iter()
+                .map(|(key, value)| {
+                    let fetched_at = DateTime::UNIX_EPOCH;
+                    (key, Entry { fetched_at, value })
+                })
+                .collect())
+        }
+    }
+}
+
+async fn store<K, V>(name: &str, entries: &HashMap<K, Entry<V>>) -> anyhow::Result<()>
+where
+    K: Eq + Hash + Serialize,
+    V: Serialize,
+{
+    tokio::fs::create_dir_all(cache_dir()).await?;
+    persist::atomic_write(&cache_file(name), &serde_json::to_vec(entries)?).await
+}
+
+/// A map persisted to `{name}.json` in the cache directory whose entries expire according to
+/// the [Policy].
+pub struct Cache<K, V> {
+    name: &'static str,
+    /// Volatile entries are only read back offline or when fetching them fails, every other
+    /// lookup fetches them again.
+    volatile: bool,
+    entries: OnceCell<RwLock<HashMap<K, Entry<V>>>>,
+    /// Sized by [concurrency] the first time something is fetched.
+    permits: OnceLock<Semaphore>,
+}
+
+impl<K, V> Cache<K, V>
+where
+    K: Eq + Hash + Serialize + DeserializeOwned,
+    V: Clone + Serialize + DeserializeOwned,
+{
+    pub const fn new(name: &'static str) -> Self {
+        Self {
+            name,
+            volatile: false,
+            entries: OnceCell::const_new(),
+            permits: OnceLock::new(),
+        }
+    }
+
+    pub const fn volatile(name: &'static str) -> Self {
+        Self {
+            name,
+            volatile: true,
+            entries: OnceCell::const_new(),
+            permits: OnceLock::new(),
+        }
+    }
+
+    /// Returns the cached value for `key`, calling `fetch` if it is missing or stale. `fetch`
+    /// returns the key to store the value under, which need not be the same as `key`.
+    ///
+    /// A stale value is still returned, with a warning, when fetching a new one fails.
+    pub async fn get_or_fetch<Q, F, Fut>(&self, key: &Q, fetch: F) -> anyhow::Result<V>
+    where
+        K: Borrow<Q>,
+        Q: Eq + Hash + fmt::Debug + ?Sized,
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = anyhow::Result<(K, V)>>,
+    {
+        let entries = self
+            .entries
+            .get_or_try_init(|| async { anyhow::Ok(RwLock::new(read(self.name).await?)) })
+            .await?;
+
+        let policy = policy();
+        let stale = match entries.read().await.get(key) {
+            Some(entry) if !self.volatile && policy.is_fresh(entry.fetched_at) => {
+                return Ok(entry.value.clone())
+            }
+            Some(entry) if policy.offline => return Ok(entry.value.clone()),
+            Some(entry) => Some(entry.value.clone()),
+            None if policy.offline => {
+                bail!(
+                    "{key:?} isn't in the {} cache and xander is offline",
+                    self.name
+                )
+            }
+            None => None,
+        };
+
+        let _permit = self
+            .permits
+            .get_or_init(|| Semaphore::new(concurrency()))
+            .acquire()
+            .await
+            .unwrap();
+        let (key, value) = match fetch().await {
+            Ok(fetched) => fetched,
+            Err(e) => match stale {
+                Some(value) => {
+                    if !quiet() {
+                        println!(
+                            "[WARN] {e:#}, using the stale copy in the {} cache",
+                            self.name
+                        );
+                    }
+                    return Ok(value);
+                }
+                None => return Err(e),
+            },
+        };
+        let mut entries = entries.write().await;
+        entries.insert(
+            key,
+            Entry {
+                fetched_at: Utc::now(),
+                value: value.clone(),
+            },
+        );
+        store(self.name, &entries).await?;
+        Ok(value)
+    }
+}
+
+pub struct Stats {
+    pub entries: usize,
+    pub stale: usize,
+    pub bytes: u64,
+}
+
+pub async fn stats(name: &str) -> anyhow::Result<Stats> {
+    let entries = read::<String, serde_json::Value>(name).await?;
+    let bytes = match tokio::fs::metadata(cache_file(name)).await {
+        Ok(metadata) => metadata.len(),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => 0,
+        Err(e) => return Err(e.into()),
+    };
+    Ok(Stats {
+        entries: entries.len(),
+        stale: entries
+            .values()
+            .filter(|entry| !policy().is_fresh(entry.fetched_at))
+            .count(),
+        bytes,
+    })
+}
+
+pub async fn clear(name: &str) -> anyhow::Result<()> {
+    match tokio::fs::remove_file(cache_file(name)).await {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e.into()),
+        _ => Ok(()),
+    }
+}
+
+/// Drops the stale entries, returning how many there were.
+pub async fn prune(name: &str) -> anyhow::Result<usize> {
+    let mut entries = read::<String, serde_json::Value>(name).await?;
+    let before = entries.len();
+    entries.retain(|_, entry| policy().is_fresh(entry.fetched_at));
+    if entries.len() != before {
+        store(name, &entries).await?;
+    }
+    Ok(before - entries.len())
+}
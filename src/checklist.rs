@@ -1,112 +1,197 @@
 use std::{
-    cell::{Ref, RefCell},
+    cell::{Cell, Ref, RefCell},
     cmp::Ordering,
-    collections::HashMap,
-    io,
     ops::Index,
-    path::PathBuf,
-    sync::OnceLock,
 };
 
-use anyhow::{bail, Context};
+use anyhow::Context;
 use futures_util::{stream, StreamExt, TryStreamExt};
-use scryfall::{card::Color, set::SetCode, Card};
+use scryfall::{
+    card::{BorderColor, Color, Game, Legality, Rarity},
+    format::Format,
+    set::{SetCode, SetType},
+    Card,
+};
 use serde::{Deserialize, Serialize};
-use tokio::sync::{OnceCell, RwLock, Semaphore};
 use uuid::Uuid;
 
-use crate::{collection::Collection, staples::Metadata, PROG_NAME};
+use crate::{
+    cache::Cache,
+    card_name::CName,
+    collection::{Collection, Version},
+    deckbuilder::{allocation::Allocation, library::Library},
+    overrides::Override,
+    prices::{Currency, Prices},
+    staples::{source::Provenance, Board, BoardMetadata, Metadata, Staple},
+};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Set {
     pub code: SetCode,
     pub name: String,
+    #[serde(default)]
+    pub collector_number: Option<String>,
+    #[serde(default)]
+    pub prices: Prices,
+    /// `None` for printings cached before these were kept, they count everywhere.
+    #[serde(default)]
+    pub rarity: Option<Rarity>,
+    #[serde(default)]
+    pub games: Option<Vec<Game>>,
+    #[serde(default)]
+    pub legal_in: Option<Vec<Format>>,
 }
 
-// TODO: dedup from super::staples
-async fn get_printings_cached(card: &Card) -> anyhow::Result<Vec<Set>> {
-    fn cache_dir() -> &'static PathBuf {
-        static CACHE_DIR: OnceLock<PathBuf> = OnceLock::new();
-        CACHE_DIR.get_or_init(|| {
-            let mut cache_dir = dirs::cache_dir().unwrap();
-            cache_dir.push(PROG_NAME);
-            cache_dir.push("printings.json");
-            cache_dir
-        })
+impl Set {
+    pub fn of_printing(card: &Card) -> Self {
+        // art series and gold bordered cards are memorabilia, alchemy cards only exist on arena
+        let playable = !matches!(card.set_type, SetType::Memorabilia | SetType::Alchemy)
+            && !matches!(card.border_color, BorderColor::Gold);
+        let legal_in = card
+            .legalities
+            .iter()
+            .filter(|_| playable)
+            .filter(|(_, legality)| matches!(legality, Legality::Legal | Legality::Restricted))
+            .map(|(format, _)| *format)
+            .collect();
+        Self {
+            code: card.set,
+            name: card.set_name.clone(),
+            collector_number: Some(card.collector_number.clone()),
+            prices: Prices::from(&card.prices),
+            rarity: Some(card.rarity),
+            games: Some(card.games.clone()),
+            legal_in: Some(legal_in),
+        }
     }
-    static STAPLE_CACHE: OnceCell<RwLock<HashMap<Uuid, Vec<Set>>>> = OnceCell::const_new();
-    static CONCURRENCY: Semaphore = Semaphore::const_new(8);
-
-    let cache = STAPLE_CACHE
-        .get_or_try_init(|| async {
-            let cards = match tokio::fs::read(cache_dir()).await {
-                Ok(cards) => cards,
-                Err(e) if e.kind() == io::ErrorKind::NotFound => {
-                    tokio::fs::create_dir_all(cache_dir().parent().unwrap()).await?;
-                    tokio::fs::File::create(cache_dir()).await?;
-                    vec![b'{', b'}']
-                }
-                Err(e) => bail!(e),
-            };
-            anyhow::Ok(RwLock::const_new(serde_json::from_slice(&cards)?))
-        })
-        .await?;
 
-    if let Some(sets) = cache.read().await.get(&card.id) {
-        return Ok(sets.clone());
+    /// Whether copies of this printing count towards the format.
+    pub fn is_eligible(&self, format: Format) -> bool {
+        let Some(info) = crate::staples::formats::info(format) else {
+            return true;
+        };
+        let rarity = !info.commons_only || self.rarity.is_none_or(|r| r == Rarity::Common);
+        let game = self
+            .games
+            .as_ref()
+            .is_none_or(|games| games.contains(&info.game));
+        let legal = self
+            .legal_in
+            .as_ref()
+            .is_none_or(|formats| formats.contains(&format));
+        rarity && game && legal
     }
+}
 
-    let _permit = CONCURRENCY.acquire().await.unwrap();
+static CACHE: Cache<Uuid, Vec<Set>> = Cache::new("printings");
 
-    let printings = card
-        .prints_search_uri
-        .fetch_iter()
+pub async fn get_printings_cached(card: &Card) -> anyhow::Result<Vec<Set>> {
+    if let Some(printings) = crate::bulk::index()
         .await?
-        .into_stream()
-        .and_then(|card| async move {
-            Ok(Set {
-                code: card.set,
-                name: scryfall::Set::code(card.set.as_ref()).await?.name,
-            })
+        .and_then(|index| index.printings(card))
+    {
+        return Ok(printings.to_vec());
+    }
+    CACHE
+        .get_or_fetch(&card.id, || async {
+            let printings = card
+                .prints_search_uri
+                .fetch_iter()
+                .await?
+                .into_stream()
+                .map_ok(|card| Set::of_printing(&card))
+                .try_collect::<Vec<_>>()
+                .await
+                .with_context(|| format!("downloading printings of {}", card.name))?;
+            if !crate::cache::quiet() {
+                println!("downloaded printings of {} ", card.name);
+            }
+            Ok((card.id, printings))
         })
-        .try_collect::<Vec<_>>()
         .await
-        .with_context(|| format!("downloading printings of {}", card.name))?;
-    let mut cache = cache.write().await;
-    cache.insert(card.id, printings.clone());
-    let cache = serde_json::to_vec::<HashMap<_, _>>(&*cache).unwrap();
-    tokio::fs::write(cache_dir(), cache).await?;
-    println!("downloaded printings of {} ", card.name);
-    Ok(printings)
 }
 
 #[derive(Debug)]
 pub struct ChecklistCard {
     pub card: Card,
     pub printings: Vec<Set>,
-    owned_versions: RefCell<Vec<SetCode>>,
+    owned_versions: RefCell<Vec<Version>>,
+    /// How many of the copies that count towards the format are kept for built decks.
+    in_built_decks: Cell<usize>,
     pub metadata: Metadata,
+    /// What each staples source said about this card, [Self::metadata] is their combination.
+    pub sources: Vec<(Provenance, Metadata)>,
+    overrides: Cell<Override>,
 }
 
 impl ChecklistCard {
-    pub fn owned_versions(&self) -> Ref<'_, Vec<SetCode>> {
+    pub fn overrides(&self) -> Override {
+        self.overrides.get()
+    }
+
+    pub fn set_overrides(&self, overrides: Override) {
+        self.overrides.set(overrides)
+    }
+
+    /// The numbers for one board with the overrides applied, `None` for ignored cards.
+    pub fn numbers(&self, board: Board) -> Option<BoardMetadata> {
+        let overrides = self.overrides();
+        if overrides.ignored {
+            return None;
+        }
+        let numbers = self.metadata.board(board)?;
+        Some(BoardMetadata {
+            num_copies: overrides.target.unwrap_or(numbers.num_copies),
+            ..numbers
+        })
+    }
+
+    pub fn owned_versions(&self) -> Ref<'_, Vec<Version>> {
         self.owned_versions.borrow()
     }
 
-    pub fn add_version(&self, code: SetCode) -> usize {
+    pub fn add_version(&self, version: Version) -> usize {
         let mut v = self.owned_versions.borrow_mut();
-        v.push(code);
+        v.push(version);
         v.len()
     }
 
-    pub fn remove_version(&self, code: SetCode) -> usize {
+    pub fn remove_version(&self, version: &Version) -> usize {
         let mut v = self.owned_versions.borrow_mut();
         v.iter()
-            .position(|s| s == &code)
+            .position(|s| s == version)
             .map(|index| v.remove(index));
         v.len()
     }
 
+    /// Whether the copy is of one of the printings that count towards the format.
+    pub fn counts(&self, version: &Version) -> bool {
+        self.printings.iter().any(|set| {
+            set.code == version.set
+                && version
+                    .collector_number
+                    .as_ref()
+                    .zip(set.collector_number.as_ref())
+                    .is_none_or(|(a, b)| a == b)
+        })
+    }
+
+    /// How many of the owned copies count towards the format and aren't kept for a built deck.
+    pub fn eligible_versions(&self) -> usize {
+        self.owned_versions()
+            .iter()
+            .filter(|version| self.counts(version))
+            .count()
+            .saturating_sub(self.in_built_decks.get())
+    }
+
+    pub fn update_version(&self, old: &Version, new: Version) {
+        let mut v = self.owned_versions.borrow_mut();
+        if let Some(version) = v.iter_mut().find(|s| *s == old) {
+            *version = new;
+        }
+    }
+
     fn cmp<F>(&self, other: &Self, missing: F) -> Ordering
     where
         F: Fn(&ChecklistCard) -> bool,
@@ -119,9 +204,14 @@ impl ChecklistCard {
                     .and_then(|c| c.colors.as_deref())
             })
         }
-        missing(self)
-            .cmp(&missing(other))
+        let rank = |card: &ChecklistCard| {
+            let overrides = card.overrides();
+            (overrides.pinned, !overrides.ignored)
+        };
+        rank(self)
+            .cmp(&rank(other))
             .reverse()
+            .then_with(|| missing(self).cmp(&missing(other)).reverse())
             .then_with(|| {
                 self.metadata
                     .percent_in_decks
@@ -132,9 +222,20 @@ impl ChecklistCard {
             .then_with(|| self.card.name.cmp(&other.card.name))
     }
 
+    /// The price of the cheapest printing, going by the card's own price if the printings
+    /// were cached before prices were kept.
+    pub fn cheapest_price(&self, currency: Currency) -> Option<f32> {
+        self.printings
+            .iter()
+            .filter_map(|set| set.prices.get(currency))
+            .min_by(f32::total_cmp)
+            .or_else(|| Prices::from(&self.card.prices).get(currency))
+    }
+
     pub fn cmp_using_collected(&self, other: &Self) -> Ordering {
         fn missing(card: &ChecklistCard) -> bool {
-            card.metadata.num_copies as usize > card.owned_versions().len()
+            card.numbers(Board::Any)
+                .is_some_and(|numbers| numbers.num_copies as usize > card.eligible_versions())
         }
         self.cmp(other, missing)
     }
@@ -144,6 +245,36 @@ impl ChecklistCard {
     }
 }
 
+/// A card on the wishlist.
+#[derive(Debug, Clone)]
+pub struct Wanted {
+    pub missing: usize,
+    pub name: String,
+    pub percent_in_decks: f32,
+    pub price: Option<f32>,
+}
+
+impl Wanted {
+    /// Play rate per unit of currency, `None` when the price is unknown.
+    pub fn value(&self) -> Option<f32> {
+        self.price
+            .map(|price| self.percent_in_decks / price.max(0.01))
+    }
+
+    /// Best value first, cards without a price go last sorted by play rate.
+    pub fn cmp_by_value(&self, other: &Self) -> Ordering {
+        match (self.value(), other.value()) {
+            (Some(a), Some(b)) => a.total_cmp(&b).reverse(),
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => self
+                .percent_in_decks
+                .total_cmp(&other.percent_in_decks)
+                .reverse(),
+        }
+    }
+}
+
 pub struct Checklist(Vec<ChecklistCard>);
 
 impl IntoIterator for Checklist {
@@ -172,52 +303,109 @@ impl Index<usize> for Checklist {
 }
 
 impl Checklist {
+    /// Only the printings that count towards the format are kept, owned copies of the others are
+    /// warned about. The format's overrides are applied on top of the staples' numbers and the
+    /// copies in built decks don't count.
     pub async fn new(
-        staples: Vec<(Card, Option<Metadata>)>,
-        collection: Collection,
+        staples: Vec<Staple>,
+        collection: &Collection,
+        library: &Library,
+        format: Format,
     ) -> anyhow::Result<Self> {
-        let mut checklist = stream::iter(
+        let overrides = crate::overrides::load(format).await?;
+        let checklist = stream::iter(
             staples
                 .into_iter()
-                .filter(|(card, _)| {
-                    card.type_line.is_none()
-                        || card
+                .filter(|staple| {
+                    staple.card.type_line.is_none()
+                        || staple
+                            .card
                             .type_line
                             .as_ref()
                             .is_some_and(|line| !line.contains("Basic"))
                 })
-                .map(|card| {
+                .map(|staple| {
+                    let name = staple.card.name.as_str().into();
                     (
-                        RefCell::new(collection.get(card.0.name.as_str().into()).into()),
-                        card,
+                        RefCell::new(collection.get(name).into()),
+                        Cell::new(overrides.get(name)),
+                        staple,
                     )
                 }),
         )
-        .map(|(versions, (card, metadata))| async move {
-            const DEFAULT_METADATA: Metadata = Metadata {
-                num_copies: 4,
-                percent_in_decks: 100.,
-            };
+        .map(|(versions, overrides, staple)| async move {
+            let mut printings = get_printings_cached(&staple.card).await?;
+            printings.retain(|set| set.is_eligible(format));
             anyhow::Ok(ChecklistCard {
                 owned_versions: versions,
-                printings: get_printings_cached(&card).await?,
-                card,
-                metadata: metadata.unwrap_or(DEFAULT_METADATA),
+                in_built_decks: Cell::new(0),
+                printings,
+                card: staple.card,
+                metadata: staple.metadata,
+                sources: staple.sources,
+                overrides,
             })
         })
-        .buffer_unordered(8)
+        .buffer_unordered(crate::cache::concurrency())
         .try_collect::<Vec<_>>()
         .await?;
 
-        checklist.sort_by(|card_a, card_b| card_a.cmp_using_collected(card_b));
+        for card in &checklist {
+            for version in card.owned_versions().iter() {
+                if !card.counts(version) {
+                    println!(
+                        "[WARN] your {} ({version}) doesn't count towards {format}",
+                        card.card.name
+                    );
+                }
+            }
+        }
 
-        Ok(Checklist(checklist))
+        let mut checklist = Checklist(checklist);
+        checklist.set_built_decks(&Allocation::new(library, collection));
+        checklist
+            .0
+            .sort_by(|card_a, card_b| card_a.cmp_using_collected(card_b));
+
+        Ok(checklist)
+    }
+
+    /// Leaves the copies the allocation keeps for built decks out of the counts.
+    pub fn set_built_decks(&self, allocation: &Allocation) {
+        for card in &self.0 {
+            let name: &CName = card.card.name.as_str().into();
+            let in_built_decks = allocation
+                .copies(name)
+                .into_iter()
+                .filter(|(version, deck)| deck.is_some() && card.counts(version))
+                .count();
+            card.in_built_decks.set(in_built_decks);
+        }
     }
 
     pub fn iter(&self) -> core::slice::Iter<'_, ChecklistCard> {
         self.0.iter()
     }
 
+    /// The copies missing from the board, best value first.
+    pub fn wishlist(&self, board: Board, currency: Currency) -> Vec<Wanted> {
+        let mut wishlist = self
+            .iter()
+            .filter_map(|card| {
+                let numbers = card.numbers(board)?;
+                Some(Wanted {
+                    missing: (numbers.num_copies as usize).saturating_sub(card.eligible_versions()),
+                    name: card.card.name.clone(),
+                    percent_in_decks: numbers.percent_in_decks,
+                    price: card.cheapest_price(currency),
+                })
+            })
+            .filter(|wanted| wanted.missing > 0)
+            .collect::<Vec<_>>();
+        wishlist.sort_by(Wanted::cmp_by_value);
+        wishlist
+    }
+
     pub fn ignoring_collection(&self) -> Vec<&ChecklistCard> {
         let mut cards = self.0.iter().collect::<Vec<_>>();
         cards.sort_by(|a, b| a.cmp_ignoring_collected(b));
@@ -0,0 +1,147 @@
+use std::{collections::HashMap, io::Write};
+
+use serde::{Deserialize, Serialize};
+
+use crate::card_name::CardName;
+
+use super::{language_name, Collection, Finish, Version};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Format {
+    /// Moxfield's collection CSV
+    Csv,
+    /// One "N Card Name (SET) 123" line per printing, foils marked with `*F*` and etched foils
+    /// with `*E*`. Conditions and languages are left out, so they're lost when it's imported
+    Text,
+    /// xander's own JSON document, see the README for the schema
+    Json,
+}
+
+const SCHEMA_VERSION: u32 = 1;
+
+/// The JSON export. Bump [SCHEMA_VERSION] whenever a field changes meaning.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Document {
+    pub schema_version: u32,
+    pub cards: Vec<Entry>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Entry {
+    pub name: CardName,
+    pub quantity: usize,
+    #[serde(flatten)]
+    pub version: Version,
+}
+
+/// Groups identical copies together, sorted by name so the output diffs nicely.
+fn entries(collection: &Collection) -> Vec<Entry> {
+    let mut entries = collection
+        .0
+        .iter()
+        .flat_map(|(name, versions)| {
+            let mut counts = HashMap::<&Version, usize>::new();
+            for version in versions {
+                *counts.entry(version).or_default() += 1;
+            }
+            counts.into_iter().map(|(version, quantity)| Entry {
+                name: name.clone(),
+                quantity,
+                version: version.clone(),
+            })
+        })
+        .collect::<Vec<_>>();
+    entries.sort_by(|a, b| {
+        a.name
+            .cmp(&b.name)
+            .then_with(|| a.version.set.as_ref().cmp(b.version.set.as_ref()))
+            .then_with(|| a.version.collector_number.cmp(&b.version.collector_number))
+            .then_with(|| a.version.to_string().cmp(&b.version.to_string()))
+    });
+    entries
+}
+
+fn csv<W: Write>(entries: &[Entry], out: W) -> anyhow::Result<()> {
+    let mut writer = csv::Writer::from_writer(out);
+    writer.write_record([
+        "Count",
+        "Tradelist Count",
+        "Name",
+        "Edition",
+        "Condition",
+        "Language",
+        "Foil",
+        "Collector Number",
+    ])?;
+    for Entry {
+        name,
+        quantity,
+        version,
+    } in entries
+    {
+        let (quantity, name, condition) = (
+            quantity.to_string(),
+            name.to_string(),
+            version.condition.to_string(),
+        );
+        writer.write_record([
+            quantity.as_str(),
+            "0",
+            &name,
+            version.set.as_ref(),
+            &condition,
+            language_name(&version.language),
+            match version.finish {
+                Finish::Nonfoil => "",
+                Finish::Foil => "foil",
+                Finish::Etched => "etched",
+            },
+            version.collector_number.as_deref().unwrap_or_default(),
+        ])?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+fn text<W: Write>(entries: &[Entry], mut out: W) -> anyhow::Result<()> {
+    for Entry {
+        name,
+        quantity,
+        version,
+    } in entries
+    {
+        write!(
+            out,
+            "{quantity} {name} ({})",
+            version.set.as_ref().to_uppercase()
+        )?;
+        if let Some(number) = &version.collector_number {
+            write!(out, " {number}")?;
+        }
+        match version.finish {
+            Finish::Nonfoil => writeln!(out)?,
+            Finish::Foil => writeln!(out, " *F*")?,
+            Finish::Etched => writeln!(out, " *E*")?,
+        }
+    }
+    Ok(())
+}
+
+pub fn export<W: Write>(collection: &Collection, format: Format, mut out: W) -> anyhow::Result<()> {
+    let entries = entries(collection);
+    match format {
+        Format::Csv => csv(&entries, out),
+        Format::Text => text(&entries, out),
+        Format::Json => {
+            serde_json::to_writer_pretty(
+                &mut out,
+                &Document {
+                    schema_version: SCHEMA_VERSION,
+                    cards: entries,
+                },
+            )?;
+            writeln!(out)?;
+            Ok(())
+        }
+    }
+}
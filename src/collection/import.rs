@@ -0,0 +1,595 @@
+use anyhow::{bail, Context};
+use csv::StringRecord;
+use futures_util::{stream, StreamExt};
+use scryfall::set::SetCode;
+
+use crate::{
+    card_name::CardName,
+    deckbuilder::{split_printing, Printing},
+};
+
+use super::{export::Document, language_code, Condition, Finish, Version};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Layout {
+    Moxfield,
+    Deckbox,
+    Manabox,
+    Archidekt,
+    /// The "N Card Name (SET)" lists written by `export --format text`
+    Text,
+    /// The documented JSON written by `export --format json`
+    Json,
+}
+
+impl Layout {
+    fn detect_csv(headers: &StringRecord) -> Option<Self> {
+        let has = |name: &str| column(headers, &[name]).is_some();
+        if has("ManaBox ID") {
+            Some(Self::Manabox)
+        } else if has("Edition Code") {
+            Some(Self::Archidekt)
+        } else if has("Tradelist Count") && has("Card Number") {
+            Some(Self::Deckbox)
+        } else if has("Tradelist Count") {
+            Some(Self::Moxfield)
+        } else {
+            None
+        }
+    }
+
+    fn columns(self, headers: &StringRecord) -> anyhow::Result<Columns> {
+        let find = |names: &[&str]| column(headers, names);
+        let require = |names: &[&str]| {
+            find(names).with_context(|| format!("{self:?} export is missing the {names:?} column"))
+        };
+        Ok(match self {
+            Self::Text | Self::Json => bail!("{self:?} exports are not CSV"),
+            Self::Moxfield => Columns {
+                quantity: require(&["Count"])?,
+                name: require(&["Name"])?,
+                set_code: find(&["Edition"]),
+                set_name: None,
+                collector_number: find(&["Collector Number"]),
+                finish: find(&["Foil"]),
+                condition: find(&["Condition"]),
+                language: find(&["Language"]),
+            },
+            Self::Deckbox => Columns {
+                quantity: require(&["Count"])?,
+                name: require(&["Name"])?,
+                set_code: None,
+                set_name: find(&["Edition"]),
+                collector_number: find(&["Card Number"]),
+                finish: find(&["Foil"]),
+                condition: find(&["Condition"]),
+                language: find(&["Language"]),
+            },
+            Self::Manabox => Columns {
+                quantity: require(&["Quantity"])?,
+                name: require(&["Name"])?,
+                set_code: find(&["Set code"]),
+                set_name: find(&["Set name"]),
+                collector_number: find(&["Collector number"]),
+                finish: find(&["Foil"]),
+                condition: find(&["Condition"]),
+                language: find(&["Language"]),
+            },
+            Self::Archidekt => Columns {
+                quantity: require(&["Quantity"])?,
+                name: require(&["Name"])?,
+                set_code: find(&["Edition Code"]),
+                set_name: find(&["Edition Name"]),
+                collector_number: find(&["Collector Number"]),
+                finish: find(&["Finish", "Foil"]),
+                condition: find(&["Condition"]),
+                language: find(&["Language"]),
+            },
+        })
+    }
+}
+
+fn column(headers: &StringRecord, names: &[&str]) -> Option<usize> {
+    headers
+        .iter()
+        .position(|h| names.iter().any(|n| h.trim().eq_ignore_ascii_case(n)))
+}
+
+struct Columns {
+    quantity: usize,
+    name: usize,
+    set_code: Option<usize>,
+    set_name: Option<usize>,
+    collector_number: Option<usize>,
+    finish: Option<usize>,
+    condition: Option<usize>,
+    language: Option<usize>,
+}
+
+struct Row {
+    line: u64,
+    quantity: usize,
+    name: CardName,
+    set_code: Option<String>,
+    set_name: Option<String>,
+    collector_number: Option<String>,
+    finish: Finish,
+    condition: Condition,
+    language: String,
+}
+
+impl Row {
+    fn version(&self, set: SetCode) -> Version {
+        Version {
+            set,
+            collector_number: self.collector_number.clone(),
+            finish: self.finish,
+            condition: self.condition,
+            language: self.language.clone(),
+        }
+    }
+}
+
+pub struct Unresolved {
+    pub line: u64,
+    pub reason: String,
+}
+
+#[derive(Default)]
+pub struct Imported {
+    pub cards: Vec<(CardName, Version)>,
+    pub unresolved: Vec<Unresolved>,
+}
+
+fn parse_finish(finish: &str) -> Finish {
+    let finish = finish.trim().to_lowercase();
+    if finish.contains("etched") {
+        Finish::Etched
+    } else if finish.contains("foil") && !finish.contains("non") {
+        Finish::Foil
+    } else {
+        Finish::Nonfoil
+    }
+}
+
+fn parse_condition(condition: &str) -> Option<Condition> {
+    let condition = condition.trim().to_lowercase().replace(['_', '-'], " ");
+    Some(match condition.as_str() {
+        "" | "m" | "mint" | "nm" | "near mint" => Condition::NearMint,
+        "lp"
+        | "ex"
+        | "sp"
+        | "excellent"
+        | "lightly played"
+        | "slightly played"
+        | "good (lightly played)" => Condition::LightlyPlayed,
+        "mp" | "gd" | "good" | "played" | "moderately played" => Condition::ModeratelyPlayed,
+        "hp" | "pl" | "heavily played" => Condition::HeavilyPlayed,
+        "d" | "dmg" | "po" | "poor" | "damaged" => Condition::Damaged,
+        _ => return None,
+    })
+}
+
+fn parse_row(columns: &Columns, record: &StringRecord) -> anyhow::Result<Option<Row>> {
+    let field = |index: Option<usize>| {
+        index
+            .and_then(|i| record.get(i))
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+    };
+    let quantity = field(Some(columns.quantity)).context("missing quantity")?;
+    let Ok(quantity) = quantity.parse::<usize>() else {
+        bail!("invalid quantity {quantity:?}");
+    };
+    if quantity == 0 {
+        return Ok(None);
+    }
+    let name = field(Some(columns.name)).context("missing card name")?;
+    let name = CardName::from(name.to_owned()).trimming_double_faced();
+
+    let condition = match field(columns.condition) {
+        Some(condition) => parse_condition(condition)
+            .with_context(|| format!("unknown condition {condition:?}"))?,
+        None => Condition::default(),
+    };
+
+    Ok(Some(Row {
+        line: record.position().map(|p| p.line()).unwrap_or_default(),
+        quantity,
+        name,
+        set_code: field(columns.set_code).map(str::to_lowercase),
+        set_name: field(columns.set_name).map(ToOwned::to_owned),
+        collector_number: field(columns.collector_number).map(ToOwned::to_owned),
+        finish: field(columns.finish).map(parse_finish).unwrap_or_default(),
+        condition,
+        language: language_code(field(columns.language).unwrap_or_default()),
+    }))
+}
+
+/// Finds the printing a row refers to, checking the card was printed in the row's set and going
+/// through the card's printings when the export only has the set's name.
+async fn resolve_set(row: &Row) -> anyhow::Result<SetCode> {
+    let card = crate::staples::get_cached(&row.name).await?;
+    let printings = crate::checklist::get_printings_cached(&card).await?;
+    let code = row
+        .set_code
+        .as_deref()
+        .and_then(|c| c.parse::<SetCode>().ok());
+    match (code, &row.set_name) {
+        (Some(code), _) => printings
+            .iter()
+            .any(|printing| printing.code == code)
+            .then_some(code)
+            .with_context(|| format!("{} was never printed in {code}", row.name)),
+        (None, Some(set_name)) => printings
+            .into_iter()
+            .find(|printing| printing.name.eq_ignore_ascii_case(set_name))
+            .map(|printing| printing.code)
+            .with_context(|| format!("{} was never printed in {set_name:?}", row.name)),
+        (None, None) => match &row.set_code {
+            Some(code) => bail!("unknown set code {code:?}"),
+            None => bail!("missing set"),
+        },
+    }
+}
+
+fn csv_rows(
+    file: &[u8],
+    layout: Option<Layout>,
+    imported: &mut Imported,
+) -> anyhow::Result<Vec<Row>> {
+    let mut reader = csv::ReaderBuilder::new().flexible(true).from_reader(file);
+    let headers = reader.headers()?.clone();
+    let layout = match layout {
+        Some(layout) => layout,
+        None => Layout::detect_csv(&headers).context("unrecognised export, pass --layout")?,
+    };
+    let columns = layout.columns(&headers)?;
+
+    let mut rows = vec![];
+    for record in reader.records() {
+        let record = match record {
+            Ok(record) => record,
+            Err(e) => {
+                imported.unresolved.push(Unresolved {
+                    line: e.position().map(|p| p.line()).unwrap_or_default(),
+                    reason: e.to_string(),
+                });
+                continue;
+            }
+        };
+        match parse_row(&columns, &record) {
+            Ok(Some(row)) => rows.push(row),
+            Ok(None) => {}
+            Err(e) => imported.unresolved.push(Unresolved {
+                line: record.position().map(|p| p.line()).unwrap_or_default(),
+                reason: format!("{e:#}"),
+            }),
+        }
+    }
+    Ok(rows)
+}
+
+/// Parses a `N Card Name (SET) 123 *F*` line, where everything after the name is optional.
+fn parse_text_line(line_number: u64, line: &str) -> anyhow::Result<Option<Row>> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with("//") || line.starts_with('#') {
+        return Ok(None);
+    }
+    let Some((quantity, rest)) = line.split_once(char::is_whitespace) else {
+        bail!("expected [count] [cardname] got {line:?}");
+    };
+    let Ok(quantity) = quantity.trim_end_matches('x').parse() else {
+        bail!("invalid quantity {quantity:?}");
+    };
+    let (name, printing, finish) = split_printing(rest.trim());
+    let (set_code, collector_number) = match printing {
+        Some(Printing {
+            set,
+            collector_number,
+        }) => (Some(set), collector_number),
+        None => (None, None),
+    };
+    Ok(Some(Row {
+        line: line_number,
+        quantity,
+        name: CardName::from(name.to_owned()).trimming_double_faced(),
+        set_code,
+        set_name: None,
+        collector_number,
+        finish,
+        condition: Condition::default(),
+        language: language_code(""),
+    }))
+}
+
+fn text_rows(file: &[u8], imported: &mut Imported) -> anyhow::Result<Vec<Row>> {
+    let file = std::str::from_utf8(file).context("text exports must be utf-8")?;
+    let mut rows = vec![];
+    for (line, text) in (1..).zip(file.lines()) {
+        match parse_text_line(line, text) {
+            Ok(Some(row)) => rows.push(row),
+            Ok(None) => {}
+            Err(e) => imported.unresolved.push(Unresolved {
+                line,
+                reason: format!("{e:#}"),
+            }),
+        }
+    }
+    Ok(rows)
+}
+
+pub async fn import(file: &[u8], layout: Option<Layout>) -> anyhow::Result<Imported> {
+    let file = file.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(file);
+    let layout = layout.or_else(|| {
+        file.trim_ascii_start()
+            .starts_with(b"{")
+            .then_some(Layout::Json)
+    });
+
+    let mut imported = Imported::default();
+    let rows = match layout {
+        Some(Layout::Json) => {
+            let document: Document = serde_json::from_slice(file)?;
+            imported.cards = document
+                .cards
+                .into_iter()
+                .flat_map(|entry| {
+                    std::iter::repeat_n(
+                        (entry.name.trimming_double_faced(), entry.version),
+                        entry.quantity,
+                    )
+                })
+                .collect();
+            return Ok(imported);
+        }
+        Some(Layout::Text) => text_rows(file, &mut imported)?,
+        layout => csv_rows(file, layout, &mut imported)?,
+    };
+
+    let resolved = stream::iter(rows)
+        .map(|row| async move {
+            let set = resolve_set(&row).await;
+            (row, set)
+        })
+        .buffered(crate::cache::concurrency())
+        .collect::<Vec<_>>()
+        .await;
+
+    for (row, set) in resolved {
+        match set {
+            Ok(set) => {
+                let version = row.version(set);
+                imported.cards.extend(
+                    std::iter::repeat_with(|| (row.name.clone(), version.clone()))
+                        .take(row.quantity),
+                );
+            }
+            Err(e) => imported.unresolved.push(Unresolved {
+                line: row.line,
+                reason: format!("{}: {e:#}", row.name),
+            }),
+        }
+    }
+
+    Ok(imported)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        super::{export::Format, Collection},
+        *,
+    };
+
+    /// Detects the layout of a one card export and parses its row.
+    fn only_row(export: &str) -> (Layout, Row) {
+        let headers = csv::Reader::from_reader(export.as_bytes())
+            .headers()
+            .unwrap()
+            .clone();
+        let layout = Layout::detect_csv(&headers).expect("layout not detected");
+        let mut imported = Imported::default();
+        let mut rows = csv_rows(export.as_bytes(), None, &mut imported).unwrap();
+        let reasons = imported
+            .unresolved
+            .iter()
+            .map(|u| u.reason.as_str())
+            .collect::<Vec<_>>();
+        assert!(reasons.is_empty(), "{reasons:?}");
+        assert_eq!(rows.len(), 1);
+        (layout, rows.remove(0))
+    }
+
+    #[test]
+    fn moxfield() {
+        let (layout, row) = only_row(
+            "\"Count\",\"Tradelist Count\",\"Name\",\"Edition\",\"Condition\",\"Language\",\"Foil\",\
+             \"Tags\",\"Last Modified\",\"Collector Number\",\"Alter\",\"Proxy\",\"Purchase Price\"\n\
+             \"2\",\"0\",\"Lightning Bolt\",\"2xm\",\"Near Mint\",\"English\",\"foil\",\"\",\
+             \"2024-01-01 10:00:00.000000\",\"141\",\"False\",\"False\",\"\"\n",
+        );
+        assert_eq!(layout, Layout::Moxfield);
+        assert_eq!(row.quantity, 2);
+        assert_eq!(row.name.to_string(), "Lightning Bolt");
+        assert_eq!(row.set_code.as_deref(), Some("2xm"));
+        assert_eq!(row.set_name, None);
+        assert_eq!(row.collector_number.as_deref(), Some("141"));
+        assert_eq!(row.finish, Finish::Foil);
+        assert_eq!(row.condition, Condition::NearMint);
+        assert_eq!(row.language, "en");
+    }
+
+    #[test]
+    fn deckbox() {
+        let (layout, row) = only_row(
+            "Count,Tradelist Count,Name,Edition,Card Number,Condition,Language,Foil,Signed,\
+             Artist Proof,Altered Art,Misprint,Promo,Textless,My Price\n\
+             1,0,Counterspell,Eternal Masters,43,Good (Lightly Played),German,,,,,,,,$1.00\n",
+        );
+        assert_eq!(layout, Layout::Deckbox);
+        assert_eq!(row.quantity, 1);
+        assert_eq!(row.name.to_string(), "Counterspell");
+        assert_eq!(row.set_code, None);
+        assert_eq!(row.set_name.as_deref(), Some("Eternal Masters"));
+        assert_eq!(row.collector_number.as_deref(), Some("43"));
+        assert_eq!(row.finish, Finish::Nonfoil);
+        assert_eq!(row.condition, Condition::LightlyPlayed);
+        assert_eq!(row.language, "de");
+    }
+
+    #[test]
+    fn manabox() {
+        let (layout, row) = only_row(
+            "Name,Set code,Set name,Collector number,Foil,Rarity,Quantity,ManaBox ID,Scryfall ID,\
+             Purchase price,Misprint,Altered,Condition,Language,Purchase price currency\n\
+             Fable of the Mirror-Breaker // Reflection of Kiki,NEO,Kamigawa: Neon Dynasty,141,\
+             normal,rare,1,81224,e3c5a3ac-7d6c-4b8a-93d6-2b1e9f0c1c2a,10.5,false,false,\
+             moderately_played,ja,EUR\n",
+        );
+        assert_eq!(layout, Layout::Manabox);
+        assert_eq!(row.quantity, 1);
+        assert_eq!(row.name.to_string(), "Fable of the Mirror-Breaker");
+        assert_eq!(row.set_code.as_deref(), Some("neo"));
+        assert_eq!(row.set_name.as_deref(), Some("Kamigawa: Neon Dynasty"));
+        assert_eq!(row.collector_number.as_deref(), Some("141"));
+        assert_eq!(row.finish, Finish::Nonfoil);
+        assert_eq!(row.condition, Condition::ModeratelyPlayed);
+        assert_eq!(row.language, "ja");
+    }
+
+    #[test]
+    fn archidekt() {
+        let (layout, row) = only_row(
+            "Quantity,Name,Finish,Condition,Date Added,Language,Purchase Price,Tags,Edition Name,\
+             Edition Code,Multiverse Id,Scryfall ID,MTGO ID,Collector Number\n\
+             4,Lightning Bolt,Etched,HP,2024-01-01,EN,,,Double Masters,2xm,,,,141\n",
+        );
+        assert_eq!(layout, Layout::Archidekt);
+        assert_eq!(row.quantity, 4);
+        assert_eq!(row.set_code.as_deref(), Some("2xm"));
+        assert_eq!(row.set_name.as_deref(), Some("Double Masters"));
+        assert_eq!(row.finish, Finish::Etched);
+        assert_eq!(row.condition, Condition::HeavilyPlayed);
+        assert_eq!(row.language, "en");
+    }
+
+    #[test]
+    fn unknown_layout() {
+        let headers = StringRecord::from(vec!["Card", "Amount"]);
+        assert_eq!(Layout::detect_csv(&headers), None);
+    }
+
+    #[test]
+    fn bad_rows_are_unresolved() {
+        let mut imported = Imported::default();
+        let rows = csv_rows(
+            b"Quantity,Name,Condition,Edition Code\nmany,Lightning Bolt,NM,2xm\n\
+              1,Lightning Bolt,chewed,2xm\n0,Lightning Bolt,NM,2xm\n",
+            None,
+            &mut imported,
+        )
+        .unwrap();
+        assert!(rows.is_empty());
+        let reasons = imported
+            .unresolved
+            .iter()
+            .map(|u| (u.line, u.reason.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            reasons,
+            [
+                (2, "invalid quantity \"many\""),
+                (3, "unknown condition \"chewed\"")
+            ]
+        );
+    }
+
+    /// A copy of everything the exports keep: finishes, collector numbers, conditions and
+    /// languages. Double faced cards are kept under their front face, like in the collection.
+    fn collection() -> Collection {
+        let version =
+            |set: &str, collector_number: Option<&str>, finish, condition, language| Version {
+                set: set.parse().unwrap(),
+                collector_number: collector_number.map(ToOwned::to_owned),
+                finish,
+                condition,
+                language: String::from(language),
+            };
+        Collection(
+            [
+                (
+                    "Lightning Bolt",
+                    vec![
+                        version("2xm", Some("141"), Finish::Foil, Condition::NearMint, "en"),
+                        version("2xm", Some("141"), Finish::Foil, Condition::NearMint, "en"),
+                        version("m10", None, Finish::Nonfoil, Condition::Damaged, "de"),
+                    ],
+                ),
+                (
+                    "Fable of the Mirror-Breaker",
+                    vec![version(
+                        "neo",
+                        Some("141"),
+                        Finish::Etched,
+                        Condition::LightlyPlayed,
+                        "ja",
+                    )],
+                ),
+            ]
+            .into_iter()
+            .map(|(name, versions)| (CardName::from(name.to_owned()), versions))
+            .collect(),
+        )
+    }
+
+    /// The cards sorted, so collections can be compared.
+    fn sorted(cards: impl IntoIterator<Item = (CardName, Version)>) -> Vec<(String, String)> {
+        let mut cards = cards
+            .into_iter()
+            .map(|(name, version)| (name.to_string(), format!("{version:?}")))
+            .collect::<Vec<_>>();
+        cards.sort();
+        cards
+    }
+
+    fn exported(format: Format) -> Vec<u8> {
+        let mut out = vec![];
+        super::super::export::export(&collection(), format, &mut out).unwrap();
+        out
+    }
+
+    #[tokio::test]
+    async fn json_round_trips() {
+        let imported = import(&exported(Format::Json), None).await.unwrap();
+        assert!(imported.unresolved.is_empty());
+        assert_eq!(
+            sorted(imported.cards),
+            sorted(collection().0.into_iter().flat_map(|(name, versions)| {
+                versions.into_iter().map(move |v| (name.clone(), v))
+            }))
+        );
+    }
+
+    #[test]
+    fn text_round_trips_without_condition_and_language() {
+        let mut imported = Imported::default();
+        let rows = text_rows(&exported(Format::Text), &mut imported).unwrap();
+        assert!(imported.unresolved.is_empty());
+        let cards = rows.iter().flat_map(|row| {
+            let version = row.version(row.set_code.as_deref().unwrap().parse().unwrap());
+            std::iter::repeat_n((row.name.clone(), version), row.quantity)
+        });
+        let expected = collection().0.into_iter().flat_map(|(name, versions)| {
+            versions.into_iter().map(move |version| {
+                let version = Version {
+                    condition: Condition::default(),
+                    language: language_code(""),
+                    ..version
+                };
+                (name.clone(), version)
+            })
+        });
+        assert_eq!(sorted(cards), sorted(expected));
+    }
+}
@@ -0,0 +1,165 @@
+use std::{collections::HashMap, fmt::Display, io, path::PathBuf, sync::OnceLock};
+
+use anyhow::bail;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::io::AsyncWriteExt;
+
+use crate::card_name::CardName;
+
+use super::{collection_file, Version, Versions};
+
+/// A single mutation of the collection.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Change {
+    Add {
+        card: CardName,
+        version: Version,
+    },
+    Del {
+        card: CardName,
+        version: Version,
+    },
+    Update {
+        card: CardName,
+        from: Version,
+        to: Version,
+    },
+}
+
+impl Change {
+    pub fn card(&self) -> &CardName {
+        match self {
+            Self::Add { card, .. } | Self::Del { card, .. } | Self::Update { card, .. } => card,
+        }
+    }
+
+    pub fn inverse(&self) -> Self {
+        match self.clone() {
+            Self::Add { card, version } => Self::Del { card, version },
+            Self::Del { card, version } => Self::Add { card, version },
+            Self::Update { card, from, to } => Self::Update {
+                card,
+                from: to,
+                to: from,
+            },
+        }
+    }
+
+    /// Returns false if the change didn't apply, e.g. when deleting a copy that isn't there.
+    pub(super) fn apply(&self, collection: &mut HashMap<CardName, Versions>) -> bool {
+        match self {
+            Self::Add { card, version } => {
+                collection
+                    .entry(card.clone())
+                    .or_default()
+                    .push(version.clone());
+                true
+            }
+            Self::Del { card, version } => collection
+                .get_mut(card)
+                .and_then(|versions| {
+                    let index = versions.iter().position(|v| v == version)?;
+                    versions.remove(index);
+                    Some(())
+                })
+                .is_some(),
+            Self::Update { card, from, to } => collection
+                .get_mut(card)
+                .and_then(|versions| versions.iter_mut().find(|v| *v == from))
+                .map(|version| *version = to.clone())
+                .is_some(),
+        }
+    }
+}
+
+impl Display for Change {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Add { card, version } => write!(f, "+ {card} [{version}]"),
+            Self::Del { card, version } => write!(f, "- {card} [{version}]"),
+            Self::Update { card, from, to } => write!(f, "~ {card} [{from}] -> [{to}]"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    Edit,
+    Undo,
+    Redo,
+}
+
+impl Display for Action {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Edit => "edit",
+            Self::Undo => "undo",
+            Self::Redo => "redo",
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Entry {
+    pub at: DateTime<Utc>,
+    pub action: Action,
+    /// The changes that were applied to the collection, for an undo these are the inverse of
+    /// the undone edit.
+    pub changes: Vec<Change>,
+}
+
+fn journal_file() -> &'static PathBuf {
+    static JOURNAL_FILE: OnceLock<PathBuf> = OnceLock::new();
+    JOURNAL_FILE.get_or_init(|| collection_file().with_file_name("history.jsonl"))
+}
+
+pub async fn read() -> anyhow::Result<Vec<Entry>> {
+    let journal = match tokio::fs::read_to_string(journal_file()).await {
+        Ok(journal) => journal,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(vec![]),
+        Err(e) => bail!(e),
+    };
+    journal
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| Ok(serde_json::from_str(line)?))
+        .collect()
+}
+
+pub(super) async fn append(action: Action, changes: Vec<Change>) -> anyhow::Result<()> {
+    let mut line = serde_json::to_vec(&Entry {
+        at: Utc::now(),
+        action,
+        changes,
+    })?;
+    line.push(b'\n');
+    let mut file = tokio::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(journal_file())
+        .await?;
+    file.write_all(&line).await?;
+    file.sync_all().await?;
+    Ok(())
+}
+
+/// Replays the journal to find the edits that can currently be undone and redone, the last
+/// element of each being the next one to apply.
+pub(super) fn stacks(journal: Vec<Entry>) -> (Vec<Vec<Change>>, Vec<Vec<Change>>) {
+    let mut undo = vec![];
+    let mut redo = vec![];
+    for entry in journal {
+        match entry.action {
+            Action::Edit => {
+                undo.push(entry.changes);
+                redo.clear();
+            }
+            Action::Undo => redo.extend(undo.pop()),
+            Action::Redo => undo.extend(redo.pop()),
+        }
+    }
+    (undo, redo)
+}
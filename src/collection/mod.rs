@@ -0,0 +1,364 @@
+pub mod export;
+pub mod import;
+pub mod journal;
+
+use std::{collections::HashMap, fmt::Display, io, path::PathBuf, sync::OnceLock};
+
+use anyhow::bail;
+use scryfall::set::SetCode;
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+
+use crate::{
+    card_name::{CName, CardName},
+    persist::{self, FileLock},
+    PROG_NAME,
+};
+
+use self::journal::{Action, Change};
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Finish {
+    #[default]
+    Nonfoil,
+    Foil,
+    Etched,
+}
+
+impl Finish {
+    pub const ALL: [Self; 3] = [Self::Nonfoil, Self::Foil, Self::Etched];
+}
+
+impl Display for Finish {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Nonfoil => "nonfoil",
+            Self::Foil => "foil",
+            Self::Etched => "etched",
+        })
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Condition {
+    #[default]
+    NearMint,
+    LightlyPlayed,
+    ModeratelyPlayed,
+    HeavilyPlayed,
+    Damaged,
+}
+
+impl Condition {
+    pub const ALL: [Self; 5] = [
+        Self::NearMint,
+        Self::LightlyPlayed,
+        Self::ModeratelyPlayed,
+        Self::HeavilyPlayed,
+        Self::Damaged,
+    ];
+
+    pub fn abbreviation(&self) -> &'static str {
+        match self {
+            Self::NearMint => "NM",
+            Self::LightlyPlayed => "LP",
+            Self::ModeratelyPlayed => "MP",
+            Self::HeavilyPlayed => "HP",
+            Self::Damaged => "DMG",
+        }
+    }
+}
+
+impl Display for Condition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::NearMint => "Near Mint",
+            Self::LightlyPlayed => "Lightly Played",
+            Self::ModeratelyPlayed => "Moderately Played",
+            Self::HeavilyPlayed => "Heavily Played",
+            Self::Damaged => "Damaged",
+        })
+    }
+}
+
+const LANGUAGES: [(&str, &str); 12] = [
+    ("en", "English"),
+    ("es", "Spanish"),
+    ("fr", "French"),
+    ("de", "German"),
+    ("it", "Italian"),
+    ("pt", "Portuguese"),
+    ("ja", "Japanese"),
+    ("ko", "Korean"),
+    ("ru", "Russian"),
+    ("zhs", "Simplified Chinese"),
+    ("zht", "Traditional Chinese"),
+    ("ph", "Phyrexian"),
+];
+
+/// Turns the language names other apps use into scryfall's language codes.
+fn language_code(language: &str) -> String {
+    let language = language.trim();
+    if language.is_empty() {
+        return default_language();
+    }
+    LANGUAGES
+        .iter()
+        .find(|(code, name)| {
+            language.eq_ignore_ascii_case(code) || language.eq_ignore_ascii_case(name)
+        })
+        .map(|(code, _)| *code)
+        .or(match language.to_lowercase().as_str() {
+            "chinese" | "chinese simplified" => Some("zhs"),
+            "chinese traditional" => Some("zht"),
+            _ => None,
+        })
+        .map(ToOwned::to_owned)
+        .unwrap_or_else(|| language.to_lowercase())
+}
+
+fn language_name(code: &str) -> &str {
+    LANGUAGES
+        .iter()
+        .find(|(c, _)| *c == code)
+        .map(|(_, name)| *name)
+        .unwrap_or(code)
+}
+
+fn default_language() -> String {
+    "en".into()
+}
+
+/// A single physical copy of a card.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Version {
+    pub set: SetCode,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub collector_number: Option<String>,
+    #[serde(default)]
+    pub finish: Finish,
+    #[serde(default)]
+    pub condition: Condition,
+    #[serde(default = "default_language")]
+    pub language: String,
+}
+
+impl Version {
+    pub fn new(set: SetCode) -> Self {
+        Self {
+            set,
+            collector_number: None,
+            finish: Finish::default(),
+            condition: Condition::default(),
+            language: default_language(),
+        }
+    }
+}
+
+impl Display for Version {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.set)?;
+        if let Some(number) = &self.collector_number {
+            write!(f, " #{number}")?;
+        }
+        if self.finish != Finish::Nonfoil {
+            write!(f, " {}", self.finish)?;
+        }
+        write!(f, " {} {}", self.condition.abbreviation(), self.language)
+    }
+}
+
+/// Collections written before copies had any detail stored just the set code.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum StoredVersion {
+    Detailed(Version),
+    SetOnly(SetCode),
+}
+
+impl StoredVersion {
+    fn is_legacy(&self) -> bool {
+        matches!(self, Self::SetOnly(_))
+    }
+}
+
+impl From<StoredVersion> for Version {
+    fn from(value: StoredVersion) -> Self {
+        match value {
+            StoredVersion::Detailed(version) => version,
+            StoredVersion::SetOnly(set) => Version::new(set),
+        }
+    }
+}
+
+type Versions = Vec<Version>;
+
+pub struct Collection(pub HashMap<CardName, Versions>);
+
+impl Collection {
+    pub fn get(&'_ self, name: &CName) -> &'_ [Version] {
+        self.0
+            .get(name.trimming_double_faced())
+            .map(|v| v.as_slice())
+            .unwrap_or(&[][..])
+    }
+
+    /// Applies a change made to the collection file to this copy of it, returning false if it
+    /// didn't apply.
+    pub fn apply(&mut self, change: &Change) -> bool {
+        change.apply(&mut self.0)
+    }
+}
+
+pub fn default_file() -> PathBuf {
+    let mut path = dirs::config_dir().unwrap();
+    path.push(PROG_NAME);
+    path.push("collection.json");
+    path
+}
+
+static COLLECTION_FILE: OnceLock<PathBuf> = OnceLock::new();
+
+/// Moves the collection for the rest of the run, must be called before it's first loaded.
+pub fn set_file(path: PathBuf) {
+    let _ = COLLECTION_FILE.set(path);
+}
+
+fn collection_file() -> &'static PathBuf {
+    COLLECTION_FILE.get_or_init(default_file)
+}
+
+/// How many previous versions of `collection.json` are kept around.
+const BACKUPS: usize = 5;
+
+/// Serializes edits coming from the UI's background tasks.
+static EDITS: Mutex<()> = Mutex::const_new(());
+
+/// Applies `changes` and journals the ones that took effect. The caller must hold the locks.
+async fn apply_locked(action: Action, changes: Vec<Change>) -> anyhow::Result<Vec<Change>> {
+    let (mut collection, _) = read().await?;
+    let applied = changes
+        .into_iter()
+        .filter(|change| change.apply(&mut collection))
+        .collect::<Vec<_>>();
+    if !applied.is_empty() {
+        store(&collection).await?;
+    }
+    // undos and redos are always journaled, even if they no longer apply, so that the undo
+    // stack moves past them.
+    if !applied.is_empty() || action != Action::Edit {
+        journal::append(action, applied.clone()).await?;
+    }
+    Ok(applied)
+}
+
+async fn apply(changes: Vec<Change>) -> anyhow::Result<()> {
+    let _guard = EDITS.lock().await;
+    let _lock = FileLock::acquire(collection_file()).await?;
+    apply_locked(Action::Edit, changes).await?;
+    Ok(())
+}
+
+/// Reverts the last edit, returning the changes made to the collection or `None` if there was
+/// nothing to undo.
+pub async fn undo() -> anyhow::Result<Option<Vec<Change>>> {
+    let _guard = EDITS.lock().await;
+    let _lock = FileLock::acquire(collection_file()).await?;
+    let (mut undo, _) = journal::stacks(journal::read().await?);
+    let Some(changes) = undo.pop() else {
+        return Ok(None);
+    };
+    let inverse = changes.iter().rev().map(Change::inverse).collect();
+    apply_locked(Action::Undo, inverse).await.map(Some)
+}
+
+/// Reapplies the last undone edit, returning the changes made to the collection or `None` if
+/// there was nothing to redo.
+pub async fn redo() -> anyhow::Result<Option<Vec<Change>>> {
+    let _guard = EDITS.lock().await;
+    let _lock = FileLock::acquire(collection_file()).await?;
+    let (_, mut redo) = journal::stacks(journal::read().await?);
+    let Some(changes) = redo.pop() else {
+        return Ok(None);
+    };
+    apply_locked(Action::Redo, changes).await.map(Some)
+}
+
+pub async fn del_from_collection(card: CardName, version: Version) -> anyhow::Result<()> {
+    let card = card.trimming_double_faced();
+    apply(vec![Change::Del { card, version }]).await
+}
+
+pub async fn add_to_collection(card: CardName, version: Version) -> anyhow::Result<()> {
+    let card = card.trimming_double_faced();
+    apply(vec![Change::Add { card, version }]).await
+}
+
+pub async fn update_in_collection(
+    card: CardName,
+    from: Version,
+    to: Version,
+) -> anyhow::Result<()> {
+    let card = card.trimming_double_faced();
+    apply(vec![Change::Update { card, from, to }]).await
+}
+
+pub async fn merge_into_collection(cards: Vec<(CardName, Version)>) -> anyhow::Result<()> {
+    apply(
+        cards
+            .into_iter()
+            .map(|(card, version)| Change::Add {
+                card: card.trimming_double_faced(),
+                version,
+            })
+            .collect(),
+    )
+    .await
+}
+
+async fn store(collection: &HashMap<CardName, Versions>) -> anyhow::Result<()> {
+    let path = collection_file();
+    tokio::fs::create_dir_all(path.parent().unwrap()).await?;
+    persist::rotate_backups(path, BACKUPS).await?;
+    persist::atomic_write(path, &serde_json::to_vec(&collection).unwrap()).await?;
+
+    Ok(())
+}
+
+/// Also returns whether the file was in the old format and was migrated.
+async fn read() -> anyhow::Result<(HashMap<CardName, Versions>, bool)> {
+    Ok(match tokio::fs::read(collection_file()).await {
+        Ok(collection) => {
+            let stored: HashMap<CardName, Vec<StoredVersion>> =
+                serde_json::from_slice(&collection)?;
+            let needs_migration = stored.values().flatten().any(StoredVersion::is_legacy);
+            let collection = stored
+                .into_iter()
+                .map(|(card, versions)| (card, versions.into_iter().map(Version::from).collect()))
+                .collect();
+            if needs_migration {
+                store(&collection).await?;
+            }
+            (collection, needs_migration)
+        }
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            let default = HashMap::default();
+            store(&default).await?;
+            (default, false)
+        }
+        Err(e) => bail!(e),
+    })
+}
+
+pub async fn load() -> anyhow::Result<Collection> {
+    let _guard = EDITS.lock().await;
+    let _lock = FileLock::acquire(collection_file()).await?;
+    let (collection, migrated) = read().await?;
+    // the ui loads it too, which mustn't print over its screen
+    if migrated && !crate::cache::quiet() {
+        println!("migrated the collection to the per-copy format");
+    }
+    Ok(Collection(collection))
+}
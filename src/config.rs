@@ -0,0 +1,291 @@
+use std::{
+    collections::BTreeMap,
+    io,
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::OnceLock,
+};
+
+use anyhow::Context;
+use scryfall::format::Format;
+use serde::{Deserialize, Deserializer, Serialize};
+
+use crate::{prices::Currency, staples::merge::MergePolicy, PROG_NAME};
+
+pub fn config_file() -> &'static PathBuf {
+    static CONFIG_FILE: OnceLock<PathBuf> = OnceLock::new();
+    CONFIG_FILE.get_or_init(|| {
+        let mut config_file = dirs::config_dir().unwrap();
+        config_file.push(PROG_NAME);
+        config_file.push("config.toml");
+        config_file
+    })
+}
+
+pub fn default_data_dir() -> PathBuf {
+    let mut data_dir = dirs::config_dir().unwrap();
+    data_dir.push(PROG_NAME);
+    data_dir
+}
+
+static DATA_DIR: OnceLock<PathBuf> = OnceLock::new();
+
+/// Moves the saved decks, overrides and snapshots for the rest of the run, must be called
+/// before any of them is read.
+pub fn set_data_dir(dir: PathBuf) {
+    let _ = DATA_DIR.set(dir);
+}
+
+pub fn data_dir() -> &'static PathBuf {
+    DATA_DIR.get_or_init(default_data_dir)
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// The format used by commands that aren't given one.
+    pub format: Format,
+    /// Where the collection is kept, its history and backups are kept next to it.
+    pub collection: PathBuf,
+    /// Where the saved decks, the overrides and the staples snapshots are kept.
+    pub data_dir: PathBuf,
+    /// How the numbers of sources that list the same card are combined.
+    pub merge: MergePolicy,
+    /// What prices are shown in.
+    pub currency: Currency,
+    /// Per staples source settings, keyed by the source's name.
+    pub sources: BTreeMap<String, SourceConfig>,
+    pub cache: CacheConfig,
+    pub network: NetworkConfig,
+    /// Colors of the ui, keyed by cursive's palette names.
+    #[serde(deserialize_with = "over_default_theme")]
+    pub theme: BTreeMap<String, String>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            format: Format::Pauper,
+            collection: crate::collection::default_file(),
+            data_dir: default_data_dir(),
+            merge: MergePolicy::default(),
+            currency: Currency::default(),
+            sources: BTreeMap::new(),
+            cache: CacheConfig::default(),
+            network: NetworkConfig::default(),
+            theme: default_theme(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CacheConfig {
+    /// How many days downloaded cards and printings are trusted for.
+    pub ttl_days: u32,
+    pub dir: PathBuf,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self {
+            ttl_days: 30,
+            dir: crate::cache::default_dir(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct NetworkConfig {
+    /// How many requests are made at the same time.
+    pub concurrency: usize,
+}
+
+impl Default for NetworkConfig {
+    fn default() -> Self {
+        Self { concurrency: 8 }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SourceConfig {
+    pub enabled: bool,
+    /// How much this source's numbers count for when merged with other sources'.
+    pub weight: f32,
+}
+
+impl Default for SourceConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            weight: 1.0,
+        }
+    }
+}
+
+fn default_theme() -> BTreeMap<String, String> {
+    [
+        ("background", "default"),
+        ("view", "default"),
+        ("shadow", "default"),
+        ("primary", "default"),
+        ("secondary", "default"),
+        ("tertiary", "default"),
+        ("title_primary", "blue"),
+        ("title_secondary", "default"),
+        ("highlight", "default"),
+        ("highlight_inactive", "white"),
+        ("highlight_text", "black"),
+    ]
+    .into_iter()
+    .map(|(key, color)| (key.into(), color.into()))
+    .collect()
+}
+
+/// The colors set in the config file replace the default ones, the rest are kept.
+fn over_default_theme<'de, D>(deserializer: D) -> Result<BTreeMap<String, String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let mut theme = default_theme();
+    theme.extend(BTreeMap::<String, String>::deserialize(deserializer)?);
+    Ok(theme)
+}
+
+/// A `NAME[=WEIGHT]` source selection, from `--source` or the environment.
+#[derive(Debug, Clone)]
+pub struct SourceArg {
+    name: &'static str,
+    weight: Option<f32>,
+}
+
+impl FromStr for SourceArg {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, weight) = match s.split_once('=') {
+            Some((name, weight)) => (
+                name,
+                Some(
+                    weight
+                        .parse()
+                        .map_err(|_| format!("invalid weight {weight:?}"))?,
+                ),
+            ),
+            None => (s, None),
+        };
+        match crate::staples::source::find(name) {
+            Some(source) => Ok(Self {
+                name: source.name(),
+                weight,
+            }),
+            None => Err(format!(
+                "unknown source {name:?}, expected one of: {}",
+                crate::staples::source::SOURCES
+                    .iter()
+                    .map(|source| source.name())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )),
+        }
+    }
+}
+
+impl Config {
+    pub fn source(&self, name: &str) -> SourceConfig {
+        self.sources.get(name).copied().unwrap_or_default()
+    }
+
+    /// Enables only the selected sources, with the weights they were given.
+    pub fn select_sources(&mut self, selected: &[SourceArg]) {
+        if selected.is_empty() {
+            return;
+        }
+        for source in crate::staples::source::SOURCES {
+            let source_config = self.sources.entry(source.name().into()).or_default();
+            let arg = selected.iter().find(|arg| arg.name == source.name());
+            source_config.enabled = arg.is_some();
+            if let Some(weight) = arg.and_then(|arg| arg.weight) {
+                source_config.weight = weight;
+            }
+        }
+    }
+}
+
+/// Reads `{PROG_NAME}_{name}`, e.g. `XANDER_FORMAT`.
+fn env(name: &str) -> Option<String> {
+    std::env::var(format!("{}_{name}", PROG_NAME.to_uppercase())).ok()
+}
+
+/// Where the config is read from: `--config`, then `XANDER_CONFIG`, then the config directory.
+pub fn path(arg: Option<&Path>) -> PathBuf {
+    arg.map(Path::to_path_buf)
+        .or_else(|| env("CONFIG").map(PathBuf::from))
+        .unwrap_or_else(|| config_file().clone())
+}
+
+/// Reads the config file, all defaults if there isn't one, and applies the `XANDER_*`
+/// environment variables on top. A file given with `--config` or `XANDER_CONFIG` has to exist.
+pub fn load(arg: Option<&Path>) -> anyhow::Result<Config> {
+    let file = path(arg);
+    let mut config: Config = match std::fs::read_to_string(&file) {
+        Ok(config) => {
+            toml::from_str(&config).with_context(|| format!("parsing {}", file.display()))?
+        }
+        Err(e) if e.kind() == io::ErrorKind::NotFound && &file == config_file() => {
+            Config::default()
+        }
+        Err(e) => return Err(e).with_context(|| format!("reading {}", file.display())),
+    };
+    for name in config.sources.keys() {
+        if crate::staples::source::find(name).is_none() {
+            println!("[WARN] unknown staples source {name:?} in the config file");
+        }
+    }
+
+    if let Some(format) = env("FORMAT") {
+        config.format = crate::staples::formats::parse(&format)
+            .with_context(|| format!("unknown format {format:?} in the environment"))?;
+    }
+    if let Some(collection) = env("COLLECTION") {
+        config.collection = collection.into();
+    }
+    if let Some(dir) = env("DATA_DIR") {
+        config.data_dir = dir.into();
+    }
+    if let Some(dir) = env("CACHE_DIR") {
+        config.cache.dir = dir.into();
+    }
+    if let Some(concurrency) = env("CONCURRENCY") {
+        config.network.concurrency = concurrency
+            .parse()
+            .with_context(|| format!("invalid concurrency {concurrency:?} in the environment"))?;
+    }
+    if let Some(sources) = env("SOURCES") {
+        let sources = sources
+            .split(',')
+            .map(str::parse)
+            .collect::<Result<Vec<SourceArg>, _>>()
+            .map_err(anyhow::Error::msg)?;
+        config.select_sources(&sources);
+    }
+    if let Some(theme) = env("THEME") {
+        for color in theme.split(',').filter(|color| !color.trim().is_empty()) {
+            let Some((key, color)) = color.split_once('=') else {
+                anyhow::bail!("expected key=color in the theme, got {color:?} in the environment");
+            };
+            config
+                .theme
+                .insert(key.trim().to_owned(), color.trim().to_owned());
+        }
+    }
+    for source in crate::staples::source::SOURCES {
+        config.sources.entry(source.name().into()).or_default();
+    }
+    if config.network.concurrency == 0 {
+        anyhow::bail!("the network concurrency has to be at least 1");
+    }
+    Ok(config)
+}
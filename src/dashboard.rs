@@ -0,0 +1,131 @@
+use std::collections::BTreeMap;
+
+use scryfall::format::Format;
+
+use crate::{
+    card_name::CardName,
+    checklist::{Checklist, ChecklistCard},
+    prices::Currency,
+    staples::Board,
+};
+
+/// How complete one format's checklist is.
+#[derive(Debug, Clone, Copy)]
+pub struct Summary {
+    pub format: Format,
+    pub owned: usize,
+    pub total: usize,
+    /// What the missing copies cost, going by each card's cheapest printing.
+    pub cost: f32,
+}
+
+impl Summary {
+    pub fn percent(&self) -> f32 {
+        if self.total == 0 {
+            100.
+        } else {
+            self.owned as f32 * 100. / self.total as f32
+        }
+    }
+}
+
+fn missing(card: &ChecklistCard) -> Option<(u8, usize)> {
+    let numbers = card.numbers(Board::Any)?;
+    let owned = usize::min(card.eligible_versions(), numbers.num_copies.into());
+    Some((numbers.num_copies, usize::from(numbers.num_copies) - owned))
+}
+
+pub fn summary(format: Format, checklist: &Checklist, currency: Currency) -> Summary {
+    checklist.iter().fold(
+        Summary {
+            format,
+            owned: 0,
+            total: 0,
+            cost: 0.,
+        },
+        |mut summary, card| {
+            if let Some((target, missing)) = missing(card) {
+                summary.total += usize::from(target);
+                summary.owned += usize::from(target) - missing;
+                summary.cost += missing as f32 * card.cheapest_price(currency).unwrap_or_default();
+            }
+            summary
+        },
+    )
+}
+
+/// A card that's a staple in more than one of the formats.
+#[derive(Debug, Clone)]
+pub struct Shared {
+    pub name: CardName,
+    /// How many copies each format wants.
+    pub targets: Vec<(Format, u8)>,
+}
+
+/// A card missing from at least one of the formats.
+#[derive(Debug, Clone)]
+pub struct Wanted {
+    pub name: CardName,
+    /// The most copies missing from any one format, copies are shared between formats.
+    pub missing: usize,
+    pub formats: Vec<Format>,
+    pub price: Option<f32>,
+}
+
+/// Every card of every checklist, by name.
+fn by_name(
+    checklists: &[(Format, Checklist)],
+) -> BTreeMap<CardName, Vec<(Format, &ChecklistCard)>> {
+    let mut cards = BTreeMap::<_, Vec<_>>::new();
+    for (format, checklist) in checklists {
+        for card in checklist {
+            cards
+                .entry(CardName::from(card.card.name.clone()).trimming_double_faced())
+                .or_default()
+                .push((*format, card));
+        }
+    }
+    cards
+}
+
+pub fn shared(checklists: &[(Format, Checklist)]) -> Vec<Shared> {
+    let mut shared = by_name(checklists)
+        .into_iter()
+        .map(|(name, cards)| Shared {
+            name,
+            targets: cards
+                .into_iter()
+                .filter_map(|(format, card)| Some((format, card.numbers(Board::Any)?.num_copies)))
+                .collect(),
+        })
+        .filter(|shared| shared.targets.len() > 1)
+        .collect::<Vec<_>>();
+    shared.sort_by_key(|shared| std::cmp::Reverse(shared.targets.len()));
+    shared
+}
+
+/// One wishlist for every format, each card listed once with the most copies any format is
+/// missing.
+pub fn wishlist(checklists: &[(Format, Checklist)], currency: Currency) -> Vec<Wanted> {
+    let mut wishlist = by_name(checklists)
+        .into_iter()
+        .filter_map(|(name, cards)| {
+            let missing = cards
+                .iter()
+                .filter_map(|(format, card)| Some((*format, missing(card)?.1)))
+                .filter(|(_, missing)| *missing > 0)
+                .collect::<Vec<_>>();
+            Some(Wanted {
+                name,
+                missing: missing.iter().map(|(_, missing)| *missing).max()?,
+                formats: missing.iter().map(|(format, _)| *format).collect(),
+                price: cards
+                    .iter()
+                    .filter_map(|(_, card)| card.cheapest_price(currency))
+                    .min_by(f32::total_cmp),
+            })
+        })
+        .collect::<Vec<_>>();
+    wishlist.sort_by_key(|wanted| std::cmp::Reverse(wanted.formats.len()));
+    wishlist
+}
@@ -0,0 +1,92 @@
+use std::collections::HashMap;
+
+use crate::{
+    card_name::{CName, CardName},
+    collection::{Collection, Version},
+};
+
+use super::{
+    library::{Library, SavedDeck},
+    Decklist, Ownership,
+};
+
+/// Which owned copies are in the decks that are built and which are free for everything else.
+pub struct Allocation {
+    /// The copies that aren't in any built deck.
+    pub free: Collection,
+    /// The copies in built decks, with the deck they're in.
+    assigned: HashMap<CardName, Vec<(Version, String)>>,
+}
+
+impl Allocation {
+    /// Built decks get their copies in the library's order, so when there aren't enough copies
+    /// for all of them the first decks keep theirs.
+    pub fn new(library: &Library, collection: &Collection) -> Self {
+        let mut free = collection.0.clone();
+        let mut assigned = HashMap::<CardName, Vec<(Version, String)>>::new();
+        for (name, deck) in library.0.iter().filter(|(_, deck)| deck.built) {
+            let pool = Collection(free);
+            let used = {
+                let mut decklist = Decklist::new(&pool);
+                for card in &deck.cards {
+                    decklist.add(card.clone());
+                }
+                let (_, used) = decklist.allocate();
+                used.into_iter()
+                    .map(|(card, used)| (card.to_owned(), used))
+                    .collect::<Vec<_>>()
+            };
+            free = pool.0;
+            for (card, used) in used {
+                let Some(versions) = free.get_mut(&card) else {
+                    continue;
+                };
+                let mut used = used.into_iter();
+                versions.retain(|version| {
+                    if used.next() != Some(true) {
+                        return true;
+                    }
+                    assigned
+                        .entry(card.clone())
+                        .or_default()
+                        .push((version.clone(), name.clone()));
+                    false
+                });
+            }
+        }
+        Self {
+            free: Collection(free),
+            assigned,
+        }
+    }
+
+    /// The copies a deck can count on: the free ones, and the ones it was given if it's built.
+    pub fn available_to(&self, deck: &str) -> Collection {
+        let mut available = self.free.0.clone();
+        for (card, copies) in &self.assigned {
+            for (version, _) in copies.iter().filter(|(_, name)| name == deck) {
+                available
+                    .entry(card.clone())
+                    .or_default()
+                    .push(version.clone());
+            }
+        }
+        Collection(available)
+    }
+
+    pub fn ownership(&self, name: &str, deck: &SavedDeck) -> Ownership {
+        super::ownership(&deck.cards, &self.available_to(name))
+    }
+
+    /// Every owned copy of the card, with the built deck it's in or `None` if it's free.
+    pub fn copies(&self, card: &CName) -> Vec<(&Version, Option<&str>)> {
+        let card = card.trimming_double_faced();
+        self.assigned
+            .get(card)
+            .into_iter()
+            .flatten()
+            .map(|(version, deck)| (version, Some(deck.as_str())))
+            .chain(self.free.get(card).iter().map(|version| (version, None)))
+            .collect()
+    }
+}
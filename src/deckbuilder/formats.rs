@@ -0,0 +1,402 @@
+use anyhow::{bail, Context};
+use quick_xml::{events::Event, Reader};
+
+use crate::collection::Finish;
+
+use super::{DeckCard, Printing, Section};
+
+/// The kinds of deck files that can be read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeckFormat {
+    /// `N Card Name` lines, optionally followed by Arena's `(SET) 123`
+    Text,
+    /// MTGO's XML `.dek`
+    Dek,
+    /// Cockatrice's XML `.cod`
+    Cod,
+    /// Forge's `.dck`, an ini file with one section per part of the deck
+    Dck,
+}
+
+impl DeckFormat {
+    pub fn detect(deck: &str) -> Self {
+        let deck = deck.trim_start();
+        if deck.starts_with('<') {
+            if deck.contains("<cockatrice_deck") {
+                Self::Cod
+            } else {
+                Self::Dek
+            }
+        } else if deck.lines().any(|line| {
+            let line = line.trim();
+            line.eq_ignore_ascii_case("[metadata]") || line.eq_ignore_ascii_case("[main]")
+        }) {
+            Self::Dck
+        } else {
+            Self::Text
+        }
+    }
+
+    pub fn parse(self, deck: &str) -> anyhow::Result<Vec<DeckCard>> {
+        match self {
+            Self::Text => parse_text(deck),
+            Self::Dek => parse_dek(deck),
+            Self::Cod => parse_cod(deck),
+            Self::Dck => parse_dck(deck),
+        }
+    }
+}
+
+/// Splits Arena's `Card Name (SET) 123 *F*` into the name, the printing, if there is one, and
+/// the finish. The collection's text import reads its lines with this too.
+pub(crate) fn split_printing(line: &str) -> (&str, Option<Printing>, Finish) {
+    let (line, finish) = if let Some(line) = line.strip_suffix("*F*") {
+        (line.trim_end(), Finish::Foil)
+    } else if let Some(line) = line.strip_suffix("*E*") {
+        (line.trim_end(), Finish::Etched)
+    } else {
+        (line, Finish::Nonfoil)
+    };
+    let Some(open) = line.rfind(" (") else {
+        return (line, None, finish);
+    };
+    let Some(close) = line[open..].find(')') else {
+        return (line, None, finish);
+    };
+    let collector_number = line[open + close + 1..].trim();
+    (
+        line[..open].trim(),
+        Some(Printing {
+            set: line[open + 2..open + close].to_lowercase(),
+            collector_number: Some(collector_number)
+                .filter(|n| !n.is_empty())
+                .map(ToOwned::to_owned),
+        }),
+        finish,
+    )
+}
+
+/// Parses a plain `N Card Name` decklist, with optional `Deck`, `Sideboard`, `Companion` and
+/// `Commander` headers or `SB:` prefixes. Without headers, the last block after a blank line is
+/// the sideboard if it has at most 15 cards. Arena's `About` block is skipped.
+fn parse_text(deck: &str) -> anyhow::Result<Vec<DeckCard>> {
+    let mut cards = vec![];
+    let mut section = Some(Section::Main);
+    let mut headers = false;
+    let mut after_blank = false;
+    let mut last_block = 0;
+    for line in deck.lines() {
+        let line = line.trim();
+        if line.is_empty() {
+            after_blank = !cards.is_empty();
+            continue;
+        }
+        if line.starts_with("//") || line.starts_with('#') {
+            continue;
+        }
+        if line.eq_ignore_ascii_case("about") {
+            section = None;
+            headers = true;
+            continue;
+        }
+        if let Some(header) = Section::from_header(line) {
+            section = Some(header);
+            headers = true;
+            continue;
+        }
+        let Some(section) = section else {
+            continue;
+        };
+        let (section, line) = match line.strip_prefix("SB:") {
+            Some(line) => (Section::Sideboard, line.trim_start()),
+            None => (section, line),
+        };
+        let Some(end_count) = line.find(|c: char| c.is_whitespace()) else {
+            bail!("expected [count] [cardname] got {line:?}");
+        };
+        let Ok(count) = line[0..end_count].trim_end_matches('x').parse::<u8>() else {
+            bail!("expected [count] [cardname] got {line:?}");
+        };
+        let (name, printing, _) = split_printing(line[end_count..].trim_start());
+        if std::mem::take(&mut after_blank) {
+            last_block = cards.len();
+        }
+        cards.push(DeckCard {
+            section,
+            name: name.to_owned(),
+            count,
+            printing,
+        });
+    }
+    let last_block_size = cards[last_block..]
+        .iter()
+        .map(|card| usize::from(card.count))
+        .sum::<usize>();
+    if !headers && last_block > 0 && last_block_size <= 15 {
+        for card in &mut cards[last_block..] {
+            card.section = Section::Sideboard;
+        }
+    }
+    Ok(cards)
+}
+
+/// Parses `<Cards Quantity="4" Sideboard="false" Name="Lightning Bolt" />` elements.
+fn parse_dek(deck: &str) -> anyhow::Result<Vec<DeckCard>> {
+    let mut reader = Reader::from_str(deck);
+    let mut cards = vec![];
+    loop {
+        match reader.read_event()? {
+            Event::Start(element) | Event::Empty(element)
+                if element.name().as_ref() == b"Cards" =>
+            {
+                let attribute = |name: &str| -> anyhow::Result<Option<String>> {
+                    Ok(match element.try_get_attribute(name)? {
+                        Some(value) => Some(value.unescape_value()?.into_owned()),
+                        None => None,
+                    })
+                };
+                let name = attribute("Name")?.context("a card without a name")?;
+                let count = attribute("Quantity")?
+                    .with_context(|| format!("{name} has no quantity"))?
+                    .parse()
+                    .with_context(|| format!("invalid quantity of {name}"))?;
+                let section = match attribute("Sideboard")?.as_deref() {
+                    Some("true") => Section::Sideboard,
+                    _ => Section::Main,
+                };
+                cards.push(DeckCard {
+                    section,
+                    name,
+                    count,
+                    printing: None,
+                });
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    Ok(cards)
+}
+
+/// Parses `<card number="4" name="Lightning Bolt"/>` elements inside `main` and `side` zones,
+/// tokens are left out.
+fn parse_cod(deck: &str) -> anyhow::Result<Vec<DeckCard>> {
+    let mut reader = Reader::from_str(deck);
+    let mut cards = vec![];
+    let mut section = None;
+    loop {
+        match reader.read_event()? {
+            Event::Start(element) if element.name().as_ref() == b"zone" => {
+                let zone = element
+                    .try_get_attribute("name")?
+                    .context("a zone without a name")?
+                    .unescape_value()?
+                    .into_owned();
+                section = match zone.as_str() {
+                    "main" => Some(Section::Main),
+                    "side" => Some(Section::Sideboard),
+                    _ => None,
+                };
+            }
+            Event::End(element) if element.name().as_ref() == b"zone" => section = None,
+            Event::Start(element) | Event::Empty(element) if element.name().as_ref() == b"card" => {
+                let Some(section) = section else {
+                    continue;
+                };
+                let name = element
+                    .try_get_attribute("name")?
+                    .context("a card without a name")?
+                    .unescape_value()?
+                    .into_owned();
+                let count = match element.try_get_attribute("number")? {
+                    Some(number) => number
+                        .unescape_value()?
+                        .parse()
+                        .with_context(|| format!("invalid number of {name}"))?,
+                    None => 1,
+                };
+                cards.push(DeckCard {
+                    section,
+                    name,
+                    count,
+                    printing: None,
+                });
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    Ok(cards)
+}
+
+/// Parses `N Card Name|SET|ART` lines under `[Main]`, `[Sideboard]` and `[Commander]`, the
+/// other sections, like `[metadata]` or `[Planes]`, are left out.
+fn parse_dck(deck: &str) -> anyhow::Result<Vec<DeckCard>> {
+    let mut cards = vec![];
+    let mut section = None;
+    for line in deck.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if let Some(header) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            section = Section::from_header(header);
+            continue;
+        }
+        let Some(section) = section else {
+            continue;
+        };
+        let Some((count, card)) = line.split_once(char::is_whitespace) else {
+            bail!("expected [count] [cardname] got {line:?}");
+        };
+        let Ok(count) = count.parse() else {
+            bail!("expected [count] [cardname] got {line:?}");
+        };
+        let mut card = card.trim().split('|');
+        let name = card.next().unwrap_or_default();
+        // the third field is forge's art index, not a collector number
+        let printing = card
+            .next()
+            .filter(|set| !set.is_empty())
+            .map(|set| Printing {
+                set: set.to_lowercase(),
+                collector_number: None,
+            });
+        cards.push(DeckCard {
+            section,
+            name: name.to_owned(),
+            count,
+            printing,
+        });
+    }
+    Ok(cards)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sections(deck: &str) -> Vec<(Section, u8)> {
+        parse_text(deck)
+            .unwrap()
+            .into_iter()
+            .map(|card| (card.section, card.count))
+            .collect()
+    }
+
+    #[test]
+    fn only_the_last_block_is_the_sideboard() {
+        let deck = "4 Lightning Bolt\n\n4 Mountain\n\n2 Pyroblast\n\n";
+        assert_eq!(
+            sections(deck),
+            [
+                (Section::Main, 4),
+                (Section::Main, 4),
+                (Section::Sideboard, 2)
+            ]
+        );
+    }
+
+    #[test]
+    fn a_big_last_block_is_not_a_sideboard() {
+        let deck = "4 Lightning Bolt\n\n16 Mountain\n";
+        assert_eq!(sections(deck), [(Section::Main, 4), (Section::Main, 16)]);
+    }
+
+    fn summary(cards: Vec<DeckCard>) -> Vec<(Section, u8, String)> {
+        cards
+            .into_iter()
+            .map(|card| (card.section, card.count, card.name))
+            .collect()
+    }
+
+    const DEK: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<Deck xmlns:xsd="http://www.w3.org/2001/XMLSchema">
+  <NetDeckID>0</NetDeckID>
+  <Cards CatID="1" Quantity="4" Sideboard="false" Name="Lightning Bolt" Annotation="0" />
+  <Cards CatID="2" Quantity="2" Sideboard="true" Name="Pyroblast" Annotation="0" />
+</Deck>"#;
+
+    const COD: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<cockatrice_deck version="1">
+    <deckname>Burn</deckname>
+    <zone name="main">
+        <card number="4" name="Lightning Bolt"/>
+    </zone>
+    <zone name="side">
+        <card number="2" name="Pyroblast"/>
+    </zone>
+    <zone name="tokens">
+        <card number="1" name="Goblin"/>
+    </zone>
+</cockatrice_deck>"#;
+
+    const DCK: &str =
+        "[metadata]\nName=Burn\n[Main]\n4 Lightning Bolt|M11|1\n[Sideboard]\n2 Pyroblast\n";
+
+    #[test]
+    fn detects_each_format() {
+        assert_eq!(DeckFormat::detect(DEK), DeckFormat::Dek);
+        assert_eq!(DeckFormat::detect(COD), DeckFormat::Cod);
+        assert_eq!(DeckFormat::detect(DCK), DeckFormat::Dck);
+        assert_eq!(DeckFormat::detect("4 Lightning Bolt\n"), DeckFormat::Text);
+    }
+
+    #[test]
+    fn dek() {
+        assert_eq!(
+            summary(parse_dek(DEK).unwrap()),
+            [
+                (Section::Main, 4, "Lightning Bolt".into()),
+                (Section::Sideboard, 2, "Pyroblast".into())
+            ]
+        );
+    }
+
+    #[test]
+    fn cod_leaves_out_tokens() {
+        assert_eq!(
+            summary(parse_cod(COD).unwrap()),
+            [
+                (Section::Main, 4, "Lightning Bolt".into()),
+                (Section::Sideboard, 2, "Pyroblast".into())
+            ]
+        );
+    }
+
+    #[test]
+    fn dck_leaves_out_metadata() {
+        let cards = parse_dck(DCK).unwrap();
+        assert_eq!(
+            cards[0].printing,
+            Some(Printing {
+                set: "m11".into(),
+                collector_number: None
+            })
+        );
+        assert_eq!(
+            summary(cards),
+            [
+                (Section::Main, 4, "Lightning Bolt".into()),
+                (Section::Sideboard, 2, "Pyroblast".into())
+            ]
+        );
+    }
+
+    #[test]
+    fn arena_line_with_a_finish() {
+        let cards = parse_text("1 Lightning Bolt (2XM) 117 *F*\n").unwrap();
+        assert_eq!(cards[0].name, "Lightning Bolt");
+        assert_eq!(
+            cards[0].printing,
+            Some(Printing {
+                set: "2xm".into(),
+                collector_number: Some("117".into())
+            })
+        );
+        assert_eq!(
+            split_printing("Lightning Bolt (2XM) 117 *F*").2,
+            Finish::Foil
+        );
+    }
+}
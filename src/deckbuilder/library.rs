@@ -0,0 +1,86 @@
+use std::{collections::BTreeMap, io, path::PathBuf};
+
+use scryfall::format::Format;
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+
+use crate::persist;
+
+use super::DeckCard;
+
+fn library_file() -> PathBuf {
+    crate::config::data_dir().join("decks.json")
+}
+
+/// A deck kept in the library, with its cards as they were when it was saved.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SavedDeck {
+    pub format: Format,
+    /// The file or url the deck was read from.
+    pub source: String,
+    pub cards: Vec<DeckCard>,
+    /// Whether the deck is put together. Its copies are kept for it, they don't count for the
+    /// other decks, the checklist, the wishlist or the statistics.
+    #[serde(default)]
+    pub built: bool,
+}
+
+/// The saved decks, keyed by their names.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Library(pub BTreeMap<String, SavedDeck>);
+
+pub async fn load() -> anyhow::Result<Library> {
+    match tokio::fs::read(library_file()).await {
+        Ok(library) => Ok(serde_json::from_slice(&library)?),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Library::default()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Serializes edits to the library file.
+static EDITS: Mutex<()> = Mutex::const_new(());
+
+async fn store(library: &Library) -> anyhow::Result<()> {
+    let path = library_file();
+    if let Some(dir) = path.parent() {
+        tokio::fs::create_dir_all(dir).await?;
+    }
+    persist::atomic_write(&path, &serde_json::to_vec_pretty(library)?).await
+}
+
+/// Saves the deck under `name`, replacing the deck saved with that name before, which is
+/// returned. A replaced deck stays built if it was.
+pub async fn save(name: String, mut deck: SavedDeck) -> anyhow::Result<Option<SavedDeck>> {
+    let _guard = EDITS.lock().await;
+    let mut library = load().await?;
+    if let Some(previous) = library.0.get(&name) {
+        deck.built |= previous.built;
+    }
+    let previous = library.0.insert(name, deck);
+    store(&library).await?;
+    Ok(previous)
+}
+
+/// Returns the removed deck, `None` if there was no deck with that name.
+pub async fn remove(name: &str) -> anyhow::Result<Option<SavedDeck>> {
+    let _guard = EDITS.lock().await;
+    let mut library = load().await?;
+    let removed = library.0.remove(name);
+    if removed.is_some() {
+        store(&library).await?;
+    }
+    Ok(removed)
+}
+
+/// Returns false if there's no deck with that name.
+pub async fn set_built(name: String, built: bool) -> anyhow::Result<bool> {
+    let _guard = EDITS.lock().await;
+    let mut library = load().await?;
+    let Some(deck) = library.0.get_mut(&name) else {
+        return Ok(false);
+    };
+    deck.built = built;
+    store(&library).await?;
+    Ok(true)
+}
@@ -0,0 +1,272 @@
+pub mod allocation;
+mod formats;
+pub mod library;
+pub mod web;
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    card_name::CName,
+    collection::{Collection, Version},
+};
+
+pub(crate) use formats::split_printing;
+pub use formats::DeckFormat;
+
+pub fn is_basic_land(name: &str) -> bool {
+    matches!(name, "Plains" | "Island" | "Swamp" | "Mountain" | "Forest")
+}
+
+/// The part of a deck a card is in. Owned copies go to the sections in this order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Section {
+    Commander,
+    Companion,
+    Main,
+    Sideboard,
+}
+
+impl Section {
+    /// Recognizes section headers like `Deck`, `Sideboard:` or mtgtop8's `SIDEBOARD`.
+    fn from_header(header: &str) -> Option<Self> {
+        match header.trim().trim_end_matches(':').to_lowercase().as_str() {
+            "deck" | "main" | "maindeck" | "main deck" | "mainboard" => Some(Self::Main),
+            "sideboard" | "side" | "sb" => Some(Self::Sideboard),
+            "companion" => Some(Self::Companion),
+            "commander" | "commanders" => Some(Self::Commander),
+            _ => None,
+        }
+    }
+
+    /// Whether the section is played when goldfishing, i.e. the main deck and the commander.
+    pub fn is_main(self) -> bool {
+        matches!(self, Self::Main | Self::Commander)
+    }
+}
+
+impl fmt::Display for Section {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Commander => "Commander",
+            Self::Companion => "Companion",
+            Self::Main => "Main",
+            Self::Sideboard => "Sideboard",
+        })
+    }
+}
+
+/// A specific printing asked for by the decklist.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct Printing {
+    /// Lowercase, like scryfall's set codes.
+    pub set: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub collector_number: Option<String>,
+}
+
+impl Printing {
+    pub fn matches(&self, version: &Version) -> bool {
+        version.set.as_ref().eq_ignore_ascii_case(&self.set)
+            && self
+                .collector_number
+                .as_ref()
+                .zip(version.collector_number.as_ref())
+                .is_none_or(|(a, b)| a == b)
+    }
+}
+
+impl fmt::Display for Printing {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "({})", self.set.to_uppercase())?;
+        if let Some(number) = &self.collector_number {
+            write!(f, " {number}")?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeckCard {
+    pub section: Section,
+    pub name: String,
+    pub count: u8,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub printing: Option<Printing>,
+}
+
+impl DeckCard {
+    fn label(&self) -> String {
+        match &self.printing {
+            Some(printing) => format!("{} {printing}", self.name),
+            None => self.name.clone(),
+        }
+    }
+}
+
+struct Entry {
+    owned: u8,
+    count: u8,
+}
+
+/// How much of a deck is owned.
+#[derive(Debug, Clone, Default)]
+pub struct Ownership {
+    pub owned: usize,
+    pub total: usize,
+    /// The copies left to get, keyed by the card and the printing asked for, if any.
+    pub missing: BTreeMap<String, u8>,
+}
+
+impl Ownership {
+    pub fn percent(&self) -> f32 {
+        if self.total == 0 {
+            100.0
+        } else {
+            self.owned as f32 * 100.0 / self.total as f32
+        }
+    }
+
+    pub fn missing_copies(&self) -> usize {
+        self.total - self.owned
+    }
+}
+
+type Entries = BTreeMap<Section, BTreeMap<String, Entry>>;
+
+struct Decklist<'c> {
+    cards: Vec<DeckCard>,
+    collection: &'c Collection,
+}
+
+impl<'c> Decklist<'c> {
+    fn new(collection: &'c Collection) -> Self {
+        Self {
+            cards: Default::default(),
+            collection,
+        }
+    }
+
+    fn add(&mut self, card: DeckCard) {
+        match self.cards.iter_mut().find(|c| {
+            c.section == card.section && c.name == card.name && c.printing == card.printing
+        }) {
+            Some(c) => c.count = c.count.saturating_add(card.count),
+            None => self.cards.push(card),
+        }
+    }
+
+    fn entries(&self) -> Entries {
+        self.allocate().0
+    }
+
+    /// Every section's cards, and which of each card's owned copies they use. Each owned copy
+    /// counts once, going to the cards that ask for its printing first and then to the earlier
+    /// sections.
+    fn allocate(&self) -> (Entries, HashMap<&CName, Vec<bool>>) {
+        let mut cards = self.cards.iter().collect::<Vec<_>>();
+        cards.sort_by_key(|card| (card.printing.is_none(), card.section));
+
+        // keyed by the front face, which is how the collection keys double faced cards
+        let mut used = HashMap::<&CName, Vec<bool>>::new();
+        let mut entries = BTreeMap::<_, BTreeMap<_, _>>::new();
+        for card in cards {
+            let owned = if is_basic_land(&card.name) {
+                card.count
+            } else {
+                let name = <&CName>::from(card.name.as_str()).trimming_double_faced();
+                let versions = self.collection.get(name);
+                let used = used
+                    .entry(name)
+                    .or_insert_with(|| vec![false; versions.len()]);
+                let mut owned = 0;
+                for (version, used) in versions.iter().zip(used) {
+                    if owned == card.count {
+                        break;
+                    }
+                    if !*used && card.printing.as_ref().is_none_or(|p| p.matches(version)) {
+                        *used = true;
+                        owned += 1;
+                    }
+                }
+                owned
+            };
+            entries.entry(card.section).or_default().insert(
+                card.label(),
+                Entry {
+                    owned,
+                    count: card.count,
+                },
+            );
+        }
+        (entries, used)
+    }
+
+    fn ownership(&self) -> Ownership {
+        let mut ownership = Ownership::default();
+        for (name, Entry { owned, count }) in self.entries().values().flatten() {
+            ownership.owned += usize::from(*owned);
+            ownership.total += usize::from(*count);
+            if owned < count {
+                let missing = ownership.missing.entry(name.clone()).or_default();
+                *missing = missing.saturating_add(count - owned);
+            }
+        }
+        ownership
+    }
+
+    fn display(&self) {
+        let entries = self.entries();
+        for (section, cards) in &entries {
+            let owned = cards.values().map(|e| usize::from(e.owned)).sum::<usize>();
+            let count = cards.values().map(|e| usize::from(e.count)).sum::<usize>();
+            println!("{section} ({owned}/{count}):");
+            for (name, Entry { owned, count }) in cards {
+                println!(
+                    "{owned}/{count}\t{}\t{name}",
+                    match u8::saturating_sub(*count, *owned) {
+                        0 => "✅",
+                        x if x < *count => "🟡",
+                        _ => "❌",
+                    }
+                )
+            }
+        }
+
+        println!("Wishlist missing:");
+        for (name, count) in self.ownership().missing {
+            println!("{count} {name}");
+        }
+    }
+}
+
+/// How much of the deck is owned, each owned copy counting once.
+pub fn ownership(cards: &[DeckCard], collection: &Collection) -> Ownership {
+    let mut decklist = Decklist::new(collection);
+    for card in cards {
+        decklist.add(card.clone());
+    }
+    decklist.ownership()
+}
+
+/// Prints which of the deck's cards are owned, section by section, and the missing copies.
+pub fn show(cards: Vec<DeckCard>, collection: &Collection, main_only: bool) {
+    let mut decklist = Decklist::new(collection);
+    for card in cards {
+        if !main_only || card.section.is_main() {
+            decklist.add(card);
+        }
+    }
+    decklist.display();
+}
+
+/// Parses a decklist in any of the [DeckFormat]s, telling them apart by their contents.
+pub fn parse_deck(deck: &str) -> anyhow::Result<Vec<DeckCard>> {
+    let deck = deck.strip_prefix('\u{feff}').unwrap_or(deck);
+    DeckFormat::detect(deck).parse(deck)
+}
@@ -0,0 +1,393 @@
+use std::collections::HashMap;
+
+use anyhow::{bail, Context};
+use reqwest::Url;
+use scraper::{Html, Selector};
+use serde::Deserialize;
+
+use crate::PROG_NAME;
+
+use super::{DeckCard, DeckFormat, Section};
+
+/// A site decks can be imported from. Downloading and parsing are kept apart, so each parser
+/// only needs the page or the JSON the site returned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Site {
+    Mtgtop8,
+    Goldfish,
+    Moxfield,
+    Archidekt,
+    Mtgdecks,
+}
+
+impl Site {
+    pub fn of(url: &Url) -> anyhow::Result<Self> {
+        let host = url.host_str().context("the url has no host")?;
+        Ok(match host.strip_prefix("www.").unwrap_or(host) {
+            "mtgtop8.com" => Self::Mtgtop8,
+            "mtggoldfish.com" => Self::Goldfish,
+            "moxfield.com" => Self::Moxfield,
+            "archidekt.com" => Self::Archidekt,
+            "mtgdecks.net" => Self::Mtgdecks,
+            host => bail!(
+                "can't import decks from {host}, only from mtgtop8.com, mtggoldfish.com, \
+                 moxfield.com, archidekt.com and mtgdecks.net"
+            ),
+        })
+    }
+
+    /// Where the deck is downloaded from, the page itself or the site's api.
+    fn download_url(self, url: &Url) -> anyhow::Result<Url> {
+        let segments = url.path_segments().map(Iterator::collect).unwrap_or(vec![]);
+        let id = |prefix: &str| {
+            segments
+                .iter()
+                .skip_while(|segment| **segment != prefix)
+                .nth(1)
+                .filter(|id| !id.is_empty())
+                .with_context(|| format!("expected a /{prefix}/<id> url, got {url}"))
+        };
+        Ok(match self {
+            Self::Mtgtop8 | Self::Mtgdecks => url.clone(),
+            Self::Goldfish => {
+                format!("https://www.mtggoldfish.com/deck/download/{}", id("deck")?).parse()?
+            }
+            Self::Moxfield => {
+                format!("https://api2.moxfield.com/v3/decks/all/{}", id("decks")?).parse()?
+            }
+            Self::Archidekt => {
+                format!("https://archidekt.com/api/decks/{}/", id("decks")?).parse()?
+            }
+        })
+    }
+
+    pub fn parse(self, body: &str) -> anyhow::Result<Vec<DeckCard>> {
+        match self {
+            Self::Mtgtop8 => parse_mtgtop8(body),
+            Self::Goldfish => DeckFormat::Text.parse(body),
+            Self::Moxfield => parse_moxfield(body),
+            Self::Archidekt => parse_archidekt(body),
+            Self::Mtgdecks => parse_mtgdecks(body),
+        }
+    }
+}
+
+/// Downloads and parses the deck at `url`, picking the importer by the url's host.
+pub async fn fetch(url: &Url) -> anyhow::Result<Vec<DeckCard>> {
+    let site = Site::of(url)?;
+    let download = site.download_url(url)?;
+    let body = reqwest::Client::builder()
+        .user_agent(PROG_NAME)
+        .build()?
+        .get(download.clone())
+        .send()
+        .await?
+        .error_for_status()
+        .with_context(|| format!("downloading {download}"))?
+        .text()
+        .await?;
+    let cards = site
+        .parse(&body)
+        .with_context(|| format!("reading the deck at {url}"))?;
+    if cards.is_empty() {
+        bail!("no cards found at {url}");
+    }
+    Ok(cards)
+}
+
+fn card(section: Section, name: &str, count: u8) -> DeckCard {
+    DeckCard {
+        section,
+        name: name.trim().to_owned(),
+        count,
+        printing: None,
+    }
+}
+
+/// Parses an mtgtop8 deck page, the sections going by the headers above the cards.
+pub fn parse_mtgtop8(html: &str) -> anyhow::Result<Vec<DeckCard>> {
+    let doc = Html::parse_document(html);
+    let selector = Selector::parse("div.O14, div.deck_line").unwrap();
+
+    let mut cards = vec![];
+    let mut section = Section::Main;
+    for element in doc.select(&selector) {
+        if element.value().classes().any(|class| class == "O14") {
+            // the main deck's headers are card types, like "21 LANDS"
+            section =
+                Section::from_header(&element.text().collect::<String>()).unwrap_or(Section::Main);
+            continue;
+        }
+        let section = match element.value().id() {
+            Some(id) if id.starts_with("sb") => Section::Sideboard,
+            _ => section,
+        };
+
+        let mut line = element.text();
+        let count: u8 = match line.next().map(|n| n.trim().parse()) {
+            Some(Ok(c)) => c,
+            Some(Err(e)) => bail!(
+                "expected a number, got {}: {e:?}",
+                element.text().next().unwrap()
+            ),
+            None => bail!("got an empty line"),
+        };
+
+        let Some(name) = line.next() else {
+            bail!("expected a card name");
+        };
+
+        cards.push(card(section, name, count));
+    }
+    Ok(cards)
+}
+
+/// Parses moxfield's `/v3/decks/all/<id>` response. The printings the deck's author picked
+/// are left out, any copy of the card counts.
+pub fn parse_moxfield(json: &str) -> anyhow::Result<Vec<DeckCard>> {
+    #[derive(Deserialize)]
+    struct Deck {
+        boards: HashMap<String, Board>,
+    }
+    #[derive(Deserialize)]
+    struct Board {
+        cards: HashMap<String, Entry>,
+    }
+    #[derive(Deserialize)]
+    struct Entry {
+        quantity: u8,
+        card: Card,
+    }
+    #[derive(Deserialize)]
+    struct Card {
+        name: String,
+    }
+
+    let deck = serde_json::from_str::<Deck>(json)?;
+    let mut cards = vec![];
+    for (board, contents) in &deck.boards {
+        let section = match board.as_str() {
+            "mainboard" => Section::Main,
+            "sideboard" => Section::Sideboard,
+            "commanders" => Section::Commander,
+            "companions" => Section::Companion,
+            // the maybeboard, tokens, attractions and the like aren't part of the deck
+            _ => continue,
+        };
+        cards.extend(
+            contents
+                .cards
+                .values()
+                .map(|entry| card(section, &entry.card.name, entry.quantity)),
+        );
+    }
+    Ok(cards)
+}
+
+/// Parses archidekt's `/api/decks/<id>/` response, cards go by their first category and the
+/// categories left out of the deck, like the maybeboard, are skipped.
+pub fn parse_archidekt(json: &str) -> anyhow::Result<Vec<DeckCard>> {
+    #[derive(Deserialize)]
+    struct Deck {
+        cards: Vec<Entry>,
+        #[serde(default)]
+        categories: Vec<Category>,
+    }
+    #[derive(Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct Category {
+        name: String,
+        included_in_deck: bool,
+    }
+    #[derive(Deserialize)]
+    struct Entry {
+        quantity: u8,
+        #[serde(default)]
+        categories: Option<Vec<String>>,
+        card: Card,
+    }
+    #[derive(Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct Card {
+        oracle_card: OracleCard,
+    }
+    #[derive(Deserialize)]
+    struct OracleCard {
+        name: String,
+    }
+
+    let deck = serde_json::from_str::<Deck>(json)?;
+    let mut cards = vec![];
+    for entry in &deck.cards {
+        let category = entry
+            .categories
+            .as_ref()
+            .and_then(|categories| categories.first());
+        let excluded = category.is_some_and(|category| {
+            category == "Maybeboard"
+                || deck
+                    .categories
+                    .iter()
+                    .any(|c| c.name == *category && !c.included_in_deck)
+        });
+        if excluded {
+            continue;
+        }
+        let section = category
+            .and_then(|category| Section::from_header(category))
+            .unwrap_or(Section::Main);
+        cards.push(card(section, &entry.card.oracle_card.name, entry.quantity));
+    }
+    Ok(cards)
+}
+
+/// Parses an mtgdecks deck page, the sections going by the table headers, like
+/// `Sideboard [15]`, above the cards.
+pub fn parse_mtgdecks(html: &str) -> anyhow::Result<Vec<DeckCard>> {
+    let doc = Html::parse_document(html);
+    let selector = Selector::parse("th, tr.cardItem").unwrap();
+    let link = Selector::parse("a.cardLink").unwrap();
+    let number = Selector::parse("td.number").unwrap();
+
+    let mut cards = vec![];
+    let mut section = Section::Main;
+    for element in doc.select(&selector) {
+        if element.value().name() == "th" {
+            let header = element.text().collect::<String>();
+            let header = header.split('[').next().unwrap_or_default();
+            section = Section::from_header(header).unwrap_or(Section::Main);
+            continue;
+        }
+        let Some(link) = element.select(&link).next() else {
+            continue;
+        };
+        let name = match link.value().attr("data-name") {
+            Some(name) => name.to_owned(),
+            None => link.text().collect(),
+        };
+        let count = match element.value().attr("data-required") {
+            Some(count) => count.to_owned(),
+            None => element
+                .select(&number)
+                .next()
+                .map(|number| number.text().collect())
+                .with_context(|| format!("no count for {name}"))?,
+        };
+        let Ok(count) = count.trim().parse() else {
+            bail!("expected a number of copies of {name}, got {count:?}");
+        };
+        cards.push(card(section, &name, count));
+    }
+    Ok(cards)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The sections, names and counts of the cards, sorted since the api's boards are maps.
+    fn parsed(site: Site, body: &str) -> Vec<(Section, String, u8)> {
+        let cards = site.parse(body).unwrap();
+        assert!(
+            cards.iter().all(|card| card.printing.is_none()),
+            "{site:?} kept the printings"
+        );
+        let mut cards = cards
+            .into_iter()
+            .map(|card| (card.section, card.name, card.count))
+            .collect::<Vec<_>>();
+        cards.sort();
+        cards
+    }
+
+    fn expected(cards: &[(Section, &str, u8)]) -> Vec<(Section, String, u8)> {
+        let mut cards = cards
+            .iter()
+            .map(|(section, name, count)| (*section, name.to_string(), *count))
+            .collect::<Vec<_>>();
+        cards.sort();
+        cards
+    }
+
+    const BURN: [(Section, &str, u8); 7] = [
+        (Section::Main, "Monastery Swiftspear", 4),
+        (Section::Main, "Kessig Flamebreather", 4),
+        (Section::Main, "Lightning Bolt", 4),
+        (Section::Main, "Chain Lightning", 4),
+        (Section::Main, "Mountain", 16),
+        (Section::Sideboard, "Pyroblast", 3),
+        (Section::Sideboard, "Smash to Smithereens", 2),
+    ];
+
+    #[test]
+    fn mtgtop8() {
+        let page = include_str!("fixtures/mtgtop8.html");
+        assert_eq!(parsed(Site::Mtgtop8, page), expected(&BURN));
+    }
+
+    #[test]
+    fn goldfish() {
+        let download = include_str!("fixtures/goldfish.txt");
+        assert_eq!(parsed(Site::Goldfish, download), expected(&BURN));
+    }
+
+    #[test]
+    fn moxfield() {
+        let response = include_str!("fixtures/moxfield.json");
+        assert_eq!(
+            parsed(Site::Moxfield, response),
+            expected(&[
+                (Section::Main, "Lightning Bolt", 4),
+                (Section::Main, "Monastery Swiftspear", 4),
+                (Section::Main, "Mountain", 16),
+                (Section::Sideboard, "Pyroblast", 3),
+            ])
+        );
+    }
+
+    #[test]
+    fn archidekt() {
+        let response = include_str!("fixtures/archidekt.json");
+        assert_eq!(
+            parsed(Site::Archidekt, response),
+            expected(&[
+                (Section::Main, "Monastery Swiftspear", 4),
+                (Section::Main, "Lightning Bolt", 4),
+                (Section::Main, "Mountain", 16),
+                (Section::Main, "Chain Lightning", 4),
+                (Section::Sideboard, "Pyroblast", 3),
+            ])
+        );
+    }
+
+    #[test]
+    fn mtgdecks() {
+        let page = include_str!("fixtures/mtgdecks.html");
+        assert_eq!(
+            parsed(Site::Mtgdecks, page),
+            expected(&[
+                (Section::Main, "Monastery Swiftspear", 4),
+                (Section::Main, "Kessig Flamebreather", 4),
+                (Section::Main, "Lightning Bolt", 4),
+                (Section::Main, "Mountain", 16),
+                (Section::Sideboard, "Pyroblast", 3),
+                (Section::Sideboard, "Smash to Smithereens", 2),
+            ])
+        );
+    }
+
+    #[test]
+    fn unknown_host() {
+        let url = "https://tappedout.net/mtg-decks/burn/".parse().unwrap();
+        let error = Site::of(&url).unwrap_err().to_string();
+        assert!(error.contains("tappedout.net"), "{error}");
+    }
+
+    #[test]
+    fn www_is_ignored() {
+        let url = "https://www.moxfield.com/decks/y4R8q0Zb2k6Jx1cVw7aT3g"
+            .parse()
+            .unwrap();
+        assert_eq!(Site::of(&url).unwrap(), Site::Moxfield);
+    }
+}
@@ -1,16 +1,32 @@
+mod bulk;
+mod cache;
 mod card_name;
 mod checklist;
 mod collection;
+mod config;
+mod dashboard;
 mod deckbuilder;
+mod overrides;
+mod persist;
+mod planner;
+mod prices;
+mod report;
 mod staples;
+mod stats;
 mod ui;
 
-use std::{convert::Infallible, path::PathBuf, str::FromStr};
+use std::{
+    convert::Infallible,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
 
-use anyhow::bail;
+use anyhow::{bail, Context};
 use checklist::Checklist;
-use clap::Parser;
+use clap::{Parser, Subcommand};
+use config::{Config, SourceArg};
 use either::Either;
+use itertools::Itertools;
 use reqwest::Url;
 use scryfall::format::Format;
 use tokio::fs::File;
@@ -18,10 +34,182 @@ use ui::panic::BACKTRACE_FILE_PATH;
 
 #[derive(Parser, Debug, Clone)]
 struct Args {
-    #[arg(default_value = "pauper")]
-    mode: Mode,
+    #[command(subcommand)]
+    command: Option<Command>,
+    /// Deprecated, use `checklist <FORMAT>` or `deck <FILE|URL>` instead
+    #[arg(hide = true)]
+    mode: Option<Mode>,
+    /// Read the config from this file instead of the one in the config directory
+    #[arg(long, global = true, value_name = "FILE")]
+    config: Option<PathBuf>,
+    /// The format used by commands that aren't given one, overrides the config file
+    #[arg(id = "default_format", long = "format", global = true, value_parser = parse_format)]
+    default_format: Option<Format>,
+    /// Only use these staples sources, optionally overriding the weight set in the config file
+    #[arg(short, long = "source", global = true, value_name = "NAME[=WEIGHT]")]
+    sources: Vec<SourceArg>,
+    /// How to combine the numbers of sources that list the same card, overrides the config file
+    #[arg(long, global = true)]
+    merge: Option<staples::merge::MergePolicy>,
+    /// Download cards and printings again even if the cached copies haven't expired
+    #[arg(long, global = true)]
+    refresh: bool,
+    /// Don't touch the network, everything comes from the caches and the bulk data
+    #[arg(long, global = true, conflicts_with = "refresh")]
+    offline: bool,
 }
 
+#[derive(Subcommand, Debug, Clone)]
+enum Command {
+    /// Open the checklist of a format's staples, this is what runs when no command is given
+    Checklist {
+        #[arg(value_parser = parse_format)]
+        format: Option<Format>,
+    },
+    /// Check how much of a decklist is owned, from a file or the deck's web page
+    Deck {
+        #[arg(value_name = "FILE|URL", value_parser = parse_deck)]
+        deck: Either<PathBuf, Url>,
+        /// Leave out the sideboard and the companion, for goldfishing
+        #[arg(long)]
+        main_only: bool,
+    },
+    /// Save decks and keep track of how much of each is owned
+    Decks {
+        #[command(subcommand)]
+        action: DecksAction,
+    },
+    /// Import, export or go through the history of the collection
+    Collection {
+        #[command(subcommand)]
+        action: CollectionAction,
+    },
+    /// Show which staples rose or fell in play rate between two runs
+    Trends {
+        #[arg(value_parser = parse_format)]
+        format: Option<Format>,
+        /// Compare against the last run on or before this day (YYYY-MM-DD) instead of the
+        /// previous run
+        #[arg(long)]
+        since: Option<chrono::NaiveDate>,
+        /// How many cards to list in each direction
+        #[arg(short = 'n', long, default_value_t = 20)]
+        count: usize,
+    },
+    /// Suggest which missing copies to buy to get the most out of a budget
+    Plan {
+        #[arg(value_parser = parse_format)]
+        format: Option<Format>,
+        #[arg(short, long)]
+        budget: f32,
+        /// Complete the N most played staples, this is the default with N = 50
+        #[arg(long, value_name = "N", group = "goal")]
+        top: Option<usize>,
+        /// Spread the budget so every color's most played staples get closer to complete
+        #[arg(long, group = "goal")]
+        colors: bool,
+        /// Complete these decklists, can be passed more than once
+        #[arg(long = "deck", value_name = "FILE", group = "goal")]
+        decks: Vec<PathBuf>,
+    },
+    /// Summarize several formats at once, with the staples they share and one wishlist for all
+    /// of them
+    Dashboard {
+        #[arg(value_parser = parse_format, required = true)]
+        formats: Vec<Format>,
+        /// Write the combined wishlist to this file instead of listing it
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Print how complete a format is without opening the ui, for scripts and bots
+    Report {
+        #[arg(value_parser = parse_format)]
+        format: Option<Format>,
+        #[arg(short, long, default_value = "json")]
+        to: report::Format,
+        #[arg(short, long, default_value = "any")]
+        board: staples::Board,
+        /// Where to write the report, defaults to stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Index a scryfall `default-cards` bulk data file, from https://scryfall.com/docs/api/bulk-data,
+    /// so cards and printings don't have to be downloaded one by one
+    Bulk { file: PathBuf },
+    /// Inspect or clean up the cards and printings downloaded from scryfall
+    Cache {
+        #[command(subcommand)]
+        action: CacheAction,
+    },
+    /// Show the settings in effect, after the config file, the environment and the flags
+    Config,
+}
+
+#[derive(Subcommand, Debug, Clone)]
+enum CollectionAction {
+    /// Merge an export from Moxfield, Deckbox, ManaBox, Archidekt or xander into the collection
+    Import {
+        /// Which app the export came from, guessed from the header when omitted
+        #[arg(short, long)]
+        layout: Option<collection::import::Layout>,
+        file: PathBuf,
+    },
+    /// Write the collection out in a format other tools understand
+    Export {
+        #[arg(short, long, default_value = "csv")]
+        to: collection::export::Format,
+        /// Where to write the export, defaults to stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// List the most recent changes made to the collection
+    History {
+        /// How many entries to list
+        #[arg(short = 'n', long, default_value_t = 20)]
+        count: usize,
+    },
+}
+
+#[derive(Subcommand, Debug, Clone)]
+enum DecksAction {
+    /// Save a decklist, from a file or the deck's web page, under a name
+    Add {
+        name: String,
+        #[arg(value_name = "FILE|URL", value_parser = parse_deck)]
+        deck: Either<PathBuf, Url>,
+        #[arg(value_parser = parse_format)]
+        format: Option<Format>,
+    },
+    /// List the saved decks with how much of each is owned
+    List,
+    /// Check how much of a saved deck is owned
+    Show {
+        name: String,
+        /// Leave out the sideboard and the companion, for goldfishing
+        #[arg(long)]
+        main_only: bool,
+    },
+    /// Forget a saved deck
+    Remove { name: String },
+    /// Mark a saved deck as put together, its copies are kept for it from then on
+    Build { name: String },
+    /// Mark a saved deck as taken apart, its copies are free again
+    Unbuild { name: String },
+    /// List every owned copy of a card and the built deck it's in
+    Where { card: String },
+}
+
+#[derive(Subcommand, Debug, Clone, Copy)]
+enum CacheAction {
+    /// Show how many entries each cache has and how many have expired
+    Stats,
+    /// Delete every cache
+    Clear,
+    /// Drop the expired entries
+    Prune,
+}
+
+/// What the lone argument meant before there were commands.
 #[derive(Debug, Clone)]
 enum Mode {
     Format(Format),
@@ -32,7 +220,7 @@ impl FromStr for Mode {
     type Err = Infallible;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        if let Some(f) = parse_format(s) {
+        if let Some(f) = staples::formats::parse(s) {
             Ok(Self::Format(f))
         } else {
             match Url::parse(s) {
@@ -43,70 +231,539 @@ impl FromStr for Mode {
     }
 }
 
-const PROG_NAME: &str = env!("CARGO_PKG_NAME");
+fn parse_format(s: &str) -> Result<Format, String> {
+    staples::formats::parse(s).ok_or_else(|| format!("unknown format {s:?}"))
+}
 
-fn parse_format(arg: &str) -> Option<Format> {
-    use fuzzy_matcher::skim::SkimMatcherV2;
-    use fuzzy_matcher::FuzzyMatcher;
-
-    static FORMAT: [(&str, Format); 5] = [
-        ("pauper", Format::Pauper),
-        ("legacy", Format::Legacy),
-        ("pioneer", Format::Pioneer),
-        ("standard", Format::Standard),
-        ("t2", Format::Standard),
-    ];
-
-    let matcher = SkimMatcherV2::default();
-    FORMAT
-        .iter()
-        .filter_map(|(format_str, format_enum)| {
-            matcher
-                .fuzzy_match(format_str, arg)
-                .map(|score| (score, format_enum))
-        })
-        .max_by_key(|(score, _)| *score)
-        .map(|(_, format)| *format)
+/// Anything that isn't an http(s) url is a path.
+fn parse_deck(s: &str) -> Result<Either<PathBuf, Url>, Infallible> {
+    match Url::parse(s) {
+        Ok(url) if matches!(url.scheme(), "http" | "https") => Ok(Either::Right(url)),
+        _ => Ok(Either::Left(s.into())),
+    }
 }
 
+const PROG_NAME: &str = env!("CARGO_PKG_NAME");
+
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    let Args { mode } = Args::parse();
+    let Args {
+        command,
+        mode,
+        config,
+        default_format,
+        sources,
+        merge,
+        refresh,
+        offline,
+    } = Args::parse();
 
+    let config_path = config::path(config.as_deref());
+    let mut config = config::load(config.as_deref())?;
+    config.select_sources(&sources);
+    if let Some(merge) = merge {
+        config.merge = merge;
+    }
+    if let Some(format) = default_format {
+        config.format = format;
+    }
+    cache::set_policy(config.cache.ttl_days, refresh, offline);
+    cache::set_dir(config.cache.dir.clone());
+    config::set_data_dir(config.data_dir.clone());
+    cache::set_concurrency(config.network.concurrency);
+    collection::set_file(config.collection.clone());
+
+    let command = match (command, mode) {
+        (Some(_), Some(mode)) => bail!("unexpected argument {mode:?}"),
+        (Some(command), None) => command,
+        (None, None) => Command::Checklist { format: None },
+        (None, Some(Mode::Format(format))) => {
+            println!(
+                "[WARN] `{PROG_NAME} <format>` is deprecated, use `{PROG_NAME} checklist {format}`"
+            );
+            Command::Checklist {
+                format: Some(format),
+            }
+        }
+        (None, Some(Mode::Deckbuilder(deck))) => {
+            let shown = deck
+                .as_ref()
+                .either(|path| path.display().to_string(), Url::to_string);
+            println!("[WARN] `{PROG_NAME} <deck>` is deprecated, use `{PROG_NAME} deck {shown}`");
+            Command::Deck {
+                deck,
+                main_only: false,
+            }
+        }
+    };
+
+    run_command(command, config, &config_path, offline).await
+}
+
+async fn checklist(format: Format, config: Config, offline: bool) -> anyhow::Result<()> {
     let collection = collection::load().await?;
+    let staples = staples::fetch(format, &config).await?;
+    if !offline {
+        staples::snapshots::save(format, &staples).await?;
+    }
+    let trends = staples::snapshots::trends(format, None).await?;
 
-    match mode {
-        Mode::Format(format) => {
-            let staples = staples::fetch(format).await?;
+    let library = deckbuilder::library::load().await?;
+    let checklist = Checklist::new(staples, &collection, &library, format).await?;
 
-            let checklist = Checklist::new(staples, collection).await?;
+    let ui_task = tokio::task::spawn_blocking(move || {
+        ui::ui(
+            checklist,
+            format,
+            trends,
+            config.currency,
+            config.theme,
+            library,
+            collection,
+        )
+    });
 
-            let ui_task = tokio::task::spawn_blocking(move || ui::ui(checklist, format));
+    ui::panic::register_backtrace_panic_handler();
 
-            ui::panic::register_backtrace_panic_handler();
+    if let Err(e) = ui_task.await {
+        match e.try_into_panic() {
+            Ok(panic) => {
+                if let Some(panic) = panic.downcast_ref::<&str>() {
+                    eprintln!("ui panicked! {panic}");
+                }
+                if let Ok(mut file) = File::open(BACKTRACE_FILE_PATH).await {
+                    let _ = tokio::io::copy(&mut file, &mut tokio::io::stdout()).await;
+                }
+            }
+            Err(e) => bail!(e),
+        }
+    }
+    Ok(())
+}
 
-            if let Err(e) = ui_task.await {
-                match e.try_into_panic() {
-                    Ok(panic) => {
-                        if let Some(panic) = panic.downcast_ref::<&str>() {
-                            eprintln!("ui panicked! {panic}");
-                        }
-                        if let Ok(mut file) = File::open(BACKTRACE_FILE_PATH).await {
-                            let _ = tokio::io::copy(&mut file, &mut tokio::io::stdout()).await;
-                        }
-                    }
-                    Err(e) => bail!(e),
+async fn run_collection_action(action: CollectionAction) -> anyhow::Result<()> {
+    match action {
+        CollectionAction::Import { layout, file } => {
+            let file = tokio::fs::read(&file).await?;
+            let imported = collection::import::import(&file, layout).await?;
+            for unresolved in &imported.unresolved {
+                println!("[WARN] line {}: {}", unresolved.line, unresolved.reason);
+            }
+            println!(
+                "imported {} copies, {} lines could not be resolved",
+                imported.cards.len(),
+                imported.unresolved.len()
+            );
+            collection::merge_into_collection(imported.cards).await?;
+        }
+        CollectionAction::Export { to, output } => {
+            let collection = collection::load().await?;
+            match output {
+                Some(path) => collection::export::export(
+                    &collection,
+                    to,
+                    std::io::BufWriter::new(std::fs::File::create(path)?),
+                )?,
+                None => collection::export::export(&collection, to, std::io::stdout().lock())?,
+            }
+        }
+        CollectionAction::History { count } => {
+            const MAX_CHANGES_SHOWN: usize = 10;
+            let journal = collection::journal::read().await?;
+            for entry in journal.iter().skip(journal.len().saturating_sub(count)) {
+                println!(
+                    "{} {}",
+                    entry
+                        .at
+                        .with_timezone(&chrono::Local)
+                        .format("%Y-%m-%d %H:%M:%S"),
+                    entry.action,
+                );
+                for change in entry.changes.iter().take(MAX_CHANGES_SHOWN) {
+                    println!("\t{change}");
+                }
+                if entry.changes.len() > MAX_CHANGES_SHOWN {
+                    println!("\t... and {} more", entry.changes.len() - MAX_CHANGES_SHOWN);
                 }
             }
         }
-        Mode::Deckbuilder(Either::Left(deck)) => {
-            let deck = File::open(&deck).await?;
-            deckbuilder::check(deck, collection).await?;
+    }
+    Ok(())
+}
+
+/// Reads a decklist file or downloads the deck from its web page.
+async fn read_deck(
+    deck: &Either<PathBuf, Url>,
+    offline: bool,
+) -> anyhow::Result<Vec<deckbuilder::DeckCard>> {
+    match deck {
+        Either::Left(path) => {
+            let text = tokio::fs::read_to_string(path)
+                .await
+                .with_context(|| format!("reading {}", path.display()))?;
+            deckbuilder::parse_deck(&text)
+        }
+        Either::Right(_) if offline => bail!("decks can't be downloaded offline"),
+        Either::Right(url) => {
+            println!("downloading {url}");
+            deckbuilder::web::fetch(url).await
+        }
+    }
+}
+
+async fn run_decks_action(
+    action: DecksAction,
+    default_format: Format,
+    offline: bool,
+) -> anyhow::Result<()> {
+    use deckbuilder::{
+        allocation::Allocation,
+        library::{self, SavedDeck},
+    };
+    match action {
+        DecksAction::Add { name, deck, format } => {
+            let cards = read_deck(&deck, offline).await?;
+            let saved = SavedDeck {
+                format: format.unwrap_or(default_format),
+                source: deck.either(|path| path.display().to_string(), |url| url.to_string()),
+                cards,
+                built: false,
+            };
+            let count = saved
+                .cards
+                .iter()
+                .map(|card| usize::from(card.count))
+                .sum::<usize>();
+            let format = saved.format;
+            match library::save(name.clone(), saved).await? {
+                Some(_) => println!("replaced {name} with a {format} deck of {count} cards"),
+                None => println!("saved {name}, a {format} deck of {count} cards"),
+            }
+        }
+        DecksAction::List => {
+            let library = library::load().await?;
+            if library.0.is_empty() {
+                println!("no saved decks, add one with `{PROG_NAME} decks add <NAME> <FILE|URL>`");
+            }
+            let allocation = Allocation::new(&library, &collection::load().await?);
+            for (name, deck) in &library.0 {
+                let ownership = allocation.ownership(name, deck);
+                println!(
+                    "{name} ({}{}): {}/{} copies ({:.1}%), {} missing",
+                    deck.format,
+                    if deck.built { ", built" } else { "" },
+                    ownership.owned,
+                    ownership.total,
+                    ownership.percent(),
+                    ownership.missing_copies()
+                );
+            }
+        }
+        DecksAction::Show { name, main_only } => {
+            let library = library::load().await?;
+            let Some(deck) = library.0.get(&name) else {
+                bail!("no deck named {name:?}, see `{PROG_NAME} decks list`");
+            };
+            println!("{name} ({}), from {}", deck.format, deck.source);
+            let allocation = Allocation::new(&library, &collection::load().await?);
+            deckbuilder::show(
+                deck.cards.clone(),
+                &allocation.available_to(&name),
+                main_only,
+            );
+        }
+        DecksAction::Remove { name } => match library::remove(&name).await? {
+            Some(_) => println!("removed {name}"),
+            None => bail!("no deck named {name:?}, see `{PROG_NAME} decks list`"),
+        },
+        DecksAction::Build { name } => {
+            if !library::set_built(name.clone(), true).await? {
+                bail!("no deck named {name:?}, see `{PROG_NAME} decks list`");
+            }
+            println!("{name} is built, its copies are kept for it");
         }
-        Mode::Deckbuilder(Either::Right(url)) => {
-            deckbuilder::load_from_web_page(url, collection).await?;
+        DecksAction::Unbuild { name } => {
+            if !library::set_built(name.clone(), false).await? {
+                bail!("no deck named {name:?}, see `{PROG_NAME} decks list`");
+            }
+            println!("{name} is taken apart, its copies are free again");
+        }
+        DecksAction::Where { card } => {
+            let collection = collection::load().await?;
+            let Some(name) = collection
+                .0
+                .keys()
+                .find(|owned| owned.eq_ignore_ascii_case(&card))
+            else {
+                bail!("you don't own any {card}");
+            };
+            let allocation = Allocation::new(&library::load().await?, &collection);
+            for (version, deck) in allocation.copies(name) {
+                println!("{version}\t{}", deck.unwrap_or("free"));
+            }
         }
     }
+    Ok(())
+}
+
+async fn run_command(
+    command: Command,
+    config: Config,
+    config_path: &Path,
+    offline: bool,
+) -> anyhow::Result<()> {
+    let default_format = config.format;
+    match command {
+        Command::Checklist { format } => {
+            checklist(format.unwrap_or(default_format), config, offline).await?
+        }
+        Command::Deck { deck, main_only } => {
+            let cards = read_deck(&deck, offline).await?;
+            // the copies in built decks aren't there to be played with
+            let allocation = deckbuilder::allocation::Allocation::new(
+                &deckbuilder::library::load().await?,
+                &collection::load().await?,
+            );
+            deckbuilder::show(cards, &allocation.free, main_only);
+        }
+        Command::Decks { action } => run_decks_action(action, default_format, offline).await?,
+        Command::Collection { action } => run_collection_action(action).await?,
+        Command::Trends {
+            format,
+            since,
+            count,
+        } => {
+            let format = format.unwrap_or(default_format);
+            let Some(trends) = staples::snapshots::trends(format, since).await? else {
+                println!("nothing to compare yet, each {format} run saves a snapshot");
+                return Ok(());
+            };
+            println!("{format}: {} -> {}", trends.since, trends.until);
+            println!("rising:");
+            for trend in trends.rising().take(count) {
+                println!("\t{trend}");
+            }
+            println!("falling:");
+            for trend in trends.falling().take(count) {
+                println!("\t{trend}");
+            }
+        }
+        Command::Plan {
+            format,
+            budget,
+            top,
+            colors,
+            decks,
+        } => {
+            let format = format.unwrap_or(default_format);
+            let goal = if colors {
+                planner::Goal::Colors
+            } else if !decks.is_empty() {
+                let mut lists = vec![];
+                for deck in &decks {
+                    let text = tokio::fs::read_to_string(deck)
+                        .await
+                        .with_context(|| format!("reading {}", deck.display()))?;
+                    lists.push(
+                        deckbuilder::parse_deck(&text)?
+                            .into_iter()
+                            .map(|card| (card.name.into(), card.count))
+                            .collect(),
+                    );
+                }
+                planner::Goal::Decks(lists)
+            } else {
+                planner::Goal::Top(top.unwrap_or(50))
+            };
+
+            let staples = staples::fetch(format, &config).await?;
+            let collection = collection::load().await?;
+            let library = deckbuilder::library::load().await?;
+            let checklist = Checklist::new(staples, &collection, &library, format).await?;
+            let mut candidates =
+                planner::candidates(&checklist, staples::Board::Any, config.currency);
+            if let planner::Goal::Decks(decks) = &goal {
+                let free = deckbuilder::allocation::Allocation::new(&library, &collection).free;
+                planner::add_deck_cards(&mut candidates, decks, &free, config.currency).await?;
+            }
+
+            let plan = planner::plan(&candidates, &goal, budget);
+            let currency = config.currency;
+            println!("{format}, {goal}, {} to spend:", currency.display(budget));
+            for purchase in &plan.purchases {
+                println!(
+                    "\t{} {} ({} each)",
+                    purchase.copies,
+                    purchase.name,
+                    currency.display(purchase.price)
+                );
+            }
+            println!("total: {}", currency.display(plan.spent));
+            if !plan.unpriced.is_empty() {
+                println!(
+                    "[WARN] no known price, left out: {}",
+                    plan.unpriced.iter().join(", ")
+                );
+            }
+        }
+        Command::Dashboard { formats, output } => {
+            let mut checklists = vec![];
+            for format in formats {
+                let staples = staples::fetch(format, &config).await?;
+                let checklist = Checklist::new(
+                    staples,
+                    &collection::load().await?,
+                    &deckbuilder::library::load().await?,
+                    format,
+                )
+                .await?;
+                checklists.push((format, checklist));
+            }
+
+            let currency = config.currency;
+            for (format, checklist) in &checklists {
+                let summary = dashboard::summary(*format, checklist, currency);
+                println!(
+                    "{format}: {}/{} copies ({:.1}%), {} to go",
+                    summary.owned,
+                    summary.total,
+                    summary.percent(),
+                    currency.display(summary.cost)
+                );
+            }
 
+            let shared = dashboard::shared(&checklists);
+            println!("staples in more than one format: {}", shared.len());
+            for card in &shared {
+                println!(
+                    "\t{} ({})",
+                    card.name,
+                    card.targets
+                        .iter()
+                        .map(|(format, target)| format!("{format} x{target}"))
+                        .join(", ")
+                );
+            }
+
+            let wishlist = dashboard::wishlist(&checklists, currency);
+            let cost = wishlist
+                .iter()
+                .map(|wanted| wanted.missing as f32 * wanted.price.unwrap_or_default())
+                .sum::<f32>();
+            println!(
+                "combined wishlist: {} copies of {} cards, {}",
+                wishlist.iter().map(|wanted| wanted.missing).sum::<usize>(),
+                wishlist.len(),
+                currency.display(cost)
+            );
+            let lines = wishlist
+                .iter()
+                .map(|wanted| format!("{} {}\n", wanted.missing, wanted.name))
+                .collect::<String>();
+            match output {
+                Some(path) => tokio::fs::write(&path, lines).await?,
+                None => {
+                    for line in lines.lines() {
+                        println!("\t{line}");
+                    }
+                }
+            }
+        }
+        Command::Report {
+            format,
+            to,
+            board,
+            output,
+        } => {
+            let format = format.unwrap_or(default_format);
+            let staples = staples::fetch(format, &config).await?;
+            let checklist = Checklist::new(
+                staples,
+                &collection::load().await?,
+                &deckbuilder::library::load().await?,
+                format,
+            )
+            .await?;
+            let report = report::build(format, &checklist, board, config.currency);
+            match output {
+                Some(path) => report::write(
+                    &report,
+                    to,
+                    std::io::BufWriter::new(std::fs::File::create(path)?),
+                )?,
+                None => report::write(&report, to, std::io::stdout().lock())?,
+            }
+        }
+        Command::Bulk { file } => {
+            println!("indexing {}, this takes a while", file.display());
+            let index = bulk::ingest(&file).await?;
+            println!(
+                "indexed {} cards and {} printings",
+                index.cards.len(),
+                index.printings.values().map(Vec::len).sum::<usize>()
+            );
+        }
+        Command::Cache { action } => {
+            for name in cache::CACHES {
+                match action {
+                    CacheAction::Stats => {
+                        let stats = cache::stats(name).await?;
+                        println!(
+                            "{name}: {} entries, {} expired, {:.1} MiB",
+                            stats.entries,
+                            stats.stale,
+                            stats.bytes as f64 / (1024. * 1024.)
+                        );
+                    }
+                    CacheAction::Clear => {
+                        cache::clear(name).await?;
+                        println!("{name}: cleared");
+                    }
+                    CacheAction::Prune => {
+                        let pruned = cache::prune(name).await?;
+                        println!("{name}: dropped {pruned} expired entries");
+                    }
+                }
+            }
+            println!("caches are kept in {}", cache::cache_dir().display());
+        }
+        Command::Config => {
+            if config_path.exists() {
+                println!("# read from {}", config_path.display());
+            } else {
+                println!("# no config file at {}", config_path.display());
+            }
+            print!("{}", toml::to_string(&config)?);
+        }
+    }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // the global flag used to share its id with the commands' format argument, so clap
+    // rejected it
+    #[test]
+    fn format_flag_next_to_a_format_argument() {
+        let args = Args::try_parse_from(["xander", "checklist", "--format", "modern"]).unwrap();
+        assert_eq!(args.default_format, Some(Format::Modern));
+        assert!(matches!(
+            args.command,
+            Some(Command::Checklist { format: None })
+        ));
+    }
+
+    #[test]
+    fn format_argument() {
+        let args = Args::try_parse_from(["xander", "checklist", "modern"]).unwrap();
+        assert_eq!(args.default_format, None);
+        assert!(matches!(
+            args.command,
+            Some(Command::Checklist {
+                format: Some(Format::Modern)
+            })
+        ));
+    }
+}
@@ -0,0 +1,71 @@
+use std::{collections::HashMap, io, path::PathBuf};
+
+use scryfall::format::Format;
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+
+use crate::{
+    card_name::{CName, CardName},
+    persist,
+};
+
+fn overrides_file(format: Format) -> PathBuf {
+    let mut path = crate::config::data_dir().join("overrides");
+    path.push(format!("{format}.json"));
+    path
+}
+
+/// What we decided about a card, on top of what the staples sources say about it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Override {
+    /// Left out of the statistics, the wishlist and purchase plans, e.g. because we proxy it.
+    pub ignored: bool,
+    /// How many copies to collect instead of the number the sources give.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub target: Option<u8>,
+    /// Always listed first.
+    pub pinned: bool,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Overrides(HashMap<CardName, Override>);
+
+impl Overrides {
+    pub fn get(&self, name: &CName) -> Override {
+        self.0
+            .get(name.trimming_double_faced())
+            .copied()
+            .unwrap_or_default()
+    }
+}
+
+pub async fn load(format: Format) -> anyhow::Result<Overrides> {
+    match tokio::fs::read(overrides_file(format)).await {
+        Ok(overrides) => Ok(serde_json::from_slice(&overrides)?),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Overrides::default()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Serializes edits coming from the UI's background tasks.
+static EDITS: Mutex<()> = Mutex::const_new(());
+
+/// Replaces the overrides of one card, cards left with nothing overridden are dropped from the
+/// file.
+pub async fn set(format: Format, card: CardName, card_override: Override) -> anyhow::Result<()> {
+    let _guard = EDITS.lock().await;
+    let mut overrides = load(format).await?;
+    let card = card.trimming_double_faced();
+    if card_override == Override::default() {
+        overrides.0.remove(&card);
+    } else {
+        overrides.0.insert(card, card_override);
+    }
+    let path = overrides_file(format);
+    if let Some(dir) = path.parent() {
+        tokio::fs::create_dir_all(dir).await?;
+    }
+    persist::atomic_write(&path, &serde_json::to_vec_pretty(&overrides)?).await
+}
@@ -0,0 +1,71 @@
+use std::{
+    ffi::OsString,
+    io,
+    path::{Path, PathBuf},
+};
+
+use tokio::io::AsyncWriteExt;
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut path = OsString::from(path);
+    path.push(suffix);
+    path.into()
+}
+
+/// Writes to a temporary file next to `path` and renames it into place, so a crash halfway
+/// through leaves the previous contents untouched.
+pub async fn atomic_write(path: &Path, contents: &[u8]) -> anyhow::Result<()> {
+    let tmp = with_suffix(path, ".tmp");
+    let mut file = tokio::fs::File::create(&tmp).await?;
+    file.write_all(contents).await?;
+    file.sync_all().await?;
+    drop(file);
+    tokio::fs::rename(&tmp, path).await?;
+    Ok(())
+}
+
+/// Copies `path` to `path.1`, shifting older copies up to `path.{count}` and dropping the oldest.
+pub async fn rotate_backups(path: &Path, count: usize) -> anyhow::Result<()> {
+    if count == 0 || !tokio::fs::try_exists(path).await? {
+        return Ok(());
+    }
+    for n in (1..count).rev() {
+        match tokio::fs::rename(
+            with_suffix(path, &format!(".{n}")),
+            with_suffix(path, &format!(".{}", n + 1)),
+        )
+        .await
+        {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e.into()),
+            _ => {}
+        }
+    }
+    tokio::fs::copy(path, with_suffix(path, ".1")).await?;
+    Ok(())
+}
+
+/// An advisory lock on `path.lock`, held until dropped. Other xander processes wait for it.
+pub struct FileLock(std::fs::File);
+
+impl FileLock {
+    pub async fn acquire(path: &Path) -> anyhow::Result<Self> {
+        let path = with_suffix(path, ".lock");
+        let file = tokio::task::spawn_blocking(move || {
+            let file = std::fs::OpenOptions::new()
+                .create(true)
+                .truncate(false)
+                .write(true)
+                .open(path)?;
+            file.lock()?;
+            io::Result::Ok(file)
+        })
+        .await??;
+        Ok(Self(file))
+    }
+}
+
+impl Drop for FileLock {
+    fn drop(&mut self) {
+        let _ = self.0.unlock();
+    }
+}
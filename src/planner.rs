@@ -0,0 +1,271 @@
+use std::collections::HashMap;
+
+use futures_util::{stream, StreamExt, TryStreamExt};
+use scryfall::Card;
+
+use crate::{
+    card_name::{CName, CardName},
+    checklist::Checklist,
+    collection::Collection,
+    prices::{Currency, Prices},
+    staples::Board,
+};
+
+/// What a purchase plan tries to get closer to completing.
+#[derive(Debug, Clone)]
+pub enum Goal {
+    /// The N most played staples.
+    Top(usize),
+    /// The most played staples of every color, spending where a color is furthest behind.
+    Colors,
+    /// These decklists, copies shared between decks are worth more.
+    Decks(Vec<Vec<(CardName, u8)>>),
+}
+
+impl std::fmt::Display for Goal {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Top(n) => write!(f, "top {n} staples"),
+            Self::Colors => f.write_str("color coverage"),
+            Self::Decks(decks) if decks.len() == 1 => f.write_str("1 deck"),
+            Self::Decks(decks) => write!(f, "{} decks", decks.len()),
+        }
+    }
+}
+
+/// A card that could be bought.
+#[derive(Debug, Clone)]
+pub struct Candidate {
+    pub name: CardName,
+    pub owned: usize,
+    pub price: Option<f32>,
+    pub percent_in_decks: f32,
+    pub num_copies: u8,
+    colors: ColorGroup,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum ColorGroup {
+    /// Index of the color in WUBRG.
+    Mono(u8),
+    Colorless,
+    Multicolor,
+    Land,
+}
+
+impl ColorGroup {
+    fn of(card: &Card) -> Self {
+        match card.colors.as_deref() {
+            _ if card.type_line.as_ref().is_some_and(|s| s.contains("Land")) => Self::Land,
+            None | Some(&[]) => Self::Colorless,
+            Some(&[c]) => Self::Mono((c as u8).trailing_zeros() as u8),
+            Some(&[_, ..]) => Self::Multicolor,
+        }
+    }
+
+    /// How many of the most played cards of this group count towards its coverage, same as in
+    /// the statistics screen.
+    fn size(self) -> usize {
+        match self {
+            Self::Mono(_) | Self::Multicolor => 20,
+            Self::Colorless | Self::Land => 10,
+        }
+    }
+}
+
+impl Candidate {
+    fn new(card: &Card, owned: usize, price: Option<f32>) -> Self {
+        Self {
+            name: card.name.clone().into(),
+            owned,
+            price,
+            percent_in_decks: 0.,
+            num_copies: 0,
+            colors: ColorGroup::of(card),
+        }
+    }
+}
+
+/// Every card on the checklist, with the numbers of the given board.
+pub fn candidates(checklist: &Checklist, board: Board, currency: Currency) -> Vec<Candidate> {
+    checklist
+        .iter()
+        .filter_map(|c| {
+            let numbers = c.numbers(board)?;
+            Some(Candidate {
+                percent_in_decks: numbers.percent_in_decks,
+                num_copies: numbers.num_copies,
+                ..Candidate::new(&c.card, c.eligible_versions(), c.cheapest_price(currency))
+            })
+        })
+        .collect()
+}
+
+/// Adds the cards of the decks that aren't staples, so they can be bought too.
+pub async fn add_deck_cards(
+    candidates: &mut Vec<Candidate>,
+    decks: &[Vec<(CardName, u8)>],
+    collection: &Collection,
+    currency: Currency,
+) -> anyhow::Result<()> {
+    let mut missing = decks
+        .iter()
+        .flatten()
+        .map(|(name, _)| name)
+        .filter(|name| !crate::deckbuilder::is_basic_land(name))
+        .filter(|name| {
+            !candidates
+                .iter()
+                .any(|c| c.name.as_slice() == name.as_slice())
+        })
+        .cloned()
+        .collect::<Vec<_>>();
+    missing.sort_unstable();
+    missing.dedup();
+
+    // owned names, so the download can be moved off the ui thread
+    let cards = stream::iter(missing)
+        .map(|name| async move { crate::staples::get_cached(&name).await })
+        .buffer_unordered(crate::cache::concurrency())
+        .try_collect::<Vec<_>>()
+        .await?;
+    candidates.extend(cards.iter().map(|card| {
+        Candidate::new(
+            card,
+            collection.get(card.name.as_str().into()).len(),
+            Prices::from(&card.prices).get(currency),
+        )
+    }));
+    Ok(())
+}
+
+#[derive(Debug, Clone)]
+pub struct Purchase {
+    pub name: CardName,
+    pub copies: usize,
+    /// The price of a single copy.
+    pub price: f32,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Plan {
+    pub purchases: Vec<Purchase>,
+    pub spent: f32,
+    /// Cards the goal wants but that have no known price.
+    pub unpriced: Vec<CardName>,
+}
+
+fn count_in(deck: &[(CardName, u8)], name: &CName) -> usize {
+    deck.iter()
+        .filter(|(n, _)| n.as_slice() == name)
+        .map(|(_, count)| usize::from(*count))
+        .sum()
+}
+
+/// Picks copies to buy, one at a time, going for the most value per unit of currency until the
+/// budget doesn't cover anything else the goal wants.
+pub fn plan(candidates: &[Candidate], goal: &Goal, budget: f32) -> Plan {
+    let mut by_percent = (0..candidates.len()).collect::<Vec<_>>();
+    by_percent.sort_by(|a, b| {
+        candidates[*a]
+            .percent_in_decks
+            .total_cmp(&candidates[*b].percent_in_decks)
+            .reverse()
+    });
+
+    // how many copies of each candidate the goal wants
+    let mut wanted = vec![0_usize; candidates.len()];
+    match goal {
+        Goal::Top(n) => {
+            for &i in by_percent.iter().take(*n) {
+                wanted[i] = candidates[i].num_copies.into();
+            }
+        }
+        Goal::Colors => {
+            let mut group_sizes = HashMap::<_, usize>::new();
+            for &i in &by_percent {
+                let c = &candidates[i];
+                let size = group_sizes.entry(c.colors).or_default();
+                if c.num_copies > 0 && *size < c.colors.size() {
+                    *size += 1;
+                    wanted[i] = c.num_copies.into();
+                }
+            }
+        }
+        Goal::Decks(decks) => {
+            for (i, c) in candidates.iter().enumerate() {
+                wanted[i] = decks
+                    .iter()
+                    .map(|deck| count_in(deck, &c.name))
+                    .max()
+                    .unwrap_or_default();
+            }
+        }
+    }
+
+    // copies owned and wanted of each color group, so the colors goal can favour the groups
+    // that are furthest behind
+    let groups = |bought: &[usize]| {
+        let mut groups = HashMap::<_, (usize, usize)>::new();
+        for (i, c) in candidates.iter().enumerate() {
+            let (owned, total) = groups.entry(c.colors).or_default();
+            *owned += usize::min(c.owned + bought[i], wanted[i]);
+            *total += wanted[i];
+        }
+        groups
+    };
+
+    let mut plan = Plan::default();
+    let mut bought = vec![0_usize; candidates.len()];
+    loop {
+        let groups = groups(&bought);
+        let value = |i: usize| {
+            let c = &candidates[i];
+            let copy = c.owned + bought[i] + 1;
+            match goal {
+                Goal::Top(_) => c.percent_in_decks,
+                Goal::Colors => {
+                    let (owned, total) = groups[&c.colors];
+                    c.percent_in_decks * (1. - owned as f32 / total.max(1) as f32)
+                }
+                Goal::Decks(decks) => decks
+                    .iter()
+                    .filter(|deck| count_in(deck, &c.name) >= copy)
+                    .count() as f32,
+            }
+        };
+        let best = candidates
+            .iter()
+            .enumerate()
+            .filter(|(i, c)| c.owned + bought[*i] < wanted[*i])
+            .filter_map(|(i, c)| Some((i, c.price?)))
+            .filter(|(_, price)| *price <= budget - plan.spent)
+            .map(|(i, price)| (i, price, value(i) / price.max(0.01)))
+            .filter(|(_, _, value)| *value > 0.)
+            .max_by(|(_, _, a), (_, _, b)| a.total_cmp(b));
+        let Some((i, price, _)) = best else {
+            break;
+        };
+        bought[i] += 1;
+        plan.spent += price;
+    }
+
+    for (i, c) in candidates.iter().enumerate() {
+        if bought[i] > 0 {
+            plan.purchases.push(Purchase {
+                name: c.name.clone(),
+                copies: bought[i],
+                price: c.price.unwrap_or_default(),
+            });
+        } else if c.price.is_none() && c.owned < wanted[i] {
+            plan.unpriced.push(c.name.clone());
+        }
+    }
+    plan.purchases.sort_by(|a, b| {
+        (b.price * b.copies as f32)
+            .total_cmp(&(a.price * a.copies as f32))
+            .then_with(|| a.name.cmp(&b.name))
+    });
+    plan.unpriced.sort();
+    plan
+}
@@ -0,0 +1,63 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Currency {
+    #[default]
+    Usd,
+    Eur,
+    /// MTGO event tickets
+    Tix,
+}
+
+impl Currency {
+    pub fn display(self, amount: f32) -> impl fmt::Display {
+        struct Amount(Currency, f32);
+        impl fmt::Display for Amount {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                match self.0 {
+                    Currency::Usd => write!(f, "${:.2}", self.1),
+                    Currency::Eur => write!(f, "€{:.2}", self.1),
+                    Currency::Tix => write!(f, "{:.2} tix", self.1),
+                }
+            }
+        }
+        Amount(self, amount)
+    }
+}
+
+/// What the cheapest finish of a printing costs.
+#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize)]
+pub struct Prices {
+    pub usd: Option<f32>,
+    pub eur: Option<f32>,
+    pub tix: Option<f32>,
+}
+
+impl Prices {
+    pub fn get(&self, currency: Currency) -> Option<f32> {
+        match currency {
+            Currency::Usd => self.usd,
+            Currency::Eur => self.eur,
+            Currency::Tix => self.tix,
+        }
+    }
+}
+
+impl From<&scryfall::card::Price> for Prices {
+    fn from(price: &scryfall::card::Price) -> Self {
+        fn cheapest(prices: &[&Option<String>]) -> Option<f32> {
+            prices
+                .iter()
+                .filter_map(|price| price.as_deref()?.parse::<f32>().ok())
+                .min_by(f32::total_cmp)
+        }
+        Self {
+            usd: cheapest(&[&price.usd, &price.usd_foil, &price.usd_etched]),
+            eur: cheapest(&[&price.eur, &price.eur_foil]),
+            tix: cheapest(&[&price.tix]),
+        }
+    }
+}
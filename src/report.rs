@@ -0,0 +1,246 @@
+use std::io::Write;
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use crate::{
+    checklist::Checklist,
+    prices::Currency,
+    staples::Board,
+    stats::{self, Progress},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Format {
+    /// The report as one JSON document, see the README for the schema
+    Json,
+    /// One row per completion figure, bucket, missing card and wishlist entry
+    Csv,
+    /// Tables meant to be pasted in a chat
+    Markdown,
+}
+
+const SCHEMA_VERSION: u32 = 1;
+
+/// Bump [SCHEMA_VERSION] whenever a field changes meaning.
+#[derive(Debug, Serialize)]
+pub struct Report {
+    pub schema_version: u32,
+    pub format: String,
+    pub board: String,
+    pub currency: Currency,
+    pub generated_at: DateTime<Utc>,
+    pub completion: Bucket,
+    pub buckets: Vec<Bucket>,
+    pub missing: Vec<MissingCard>,
+    pub wishlist: Vec<WishlistEntry>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Bucket {
+    pub name: String,
+    pub owned: u16,
+    pub total: u16,
+    pub cost: f32,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MissingCard {
+    pub name: String,
+    pub owned: usize,
+    pub target: u8,
+    pub percent_in_decks: f32,
+    pub price: Option<f32>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct WishlistEntry {
+    pub name: String,
+    pub missing: usize,
+    pub price: Option<f32>,
+}
+
+impl Bucket {
+    fn new(name: String, progress: Progress) -> Self {
+        Self {
+            name,
+            owned: progress.owned,
+            total: progress.total,
+            cost: progress.cost,
+        }
+    }
+}
+
+pub fn build(
+    format: scryfall::format::Format,
+    checklist: &Checklist,
+    board: Board,
+    currency: Currency,
+) -> Report {
+    let stats = stats::calculate(checklist, board, currency);
+    Report {
+        schema_version: SCHEMA_VERSION,
+        format: format.to_string(),
+        board: board.to_string(),
+        currency,
+        generated_at: Utc::now(),
+        completion: Bucket::new(format.to_string(), stats.everything),
+        buckets: stats
+            .buckets()
+            .into_iter()
+            .map(|(name, progress)| Bucket::new(name, progress))
+            .collect(),
+        missing: checklist
+            .iter()
+            .filter_map(|card| {
+                let numbers = card.numbers(board)?;
+                let owned = card.eligible_versions();
+                (owned < numbers.num_copies.into()).then(|| MissingCard {
+                    name: card.card.name.clone(),
+                    owned,
+                    target: numbers.num_copies,
+                    percent_in_decks: numbers.percent_in_decks,
+                    price: card.cheapest_price(currency),
+                })
+            })
+            .collect(),
+        wishlist: checklist
+            .wishlist(board, currency)
+            .into_iter()
+            .map(|wanted| WishlistEntry {
+                name: wanted.name,
+                missing: wanted.missing,
+                price: wanted.price,
+            })
+            .collect(),
+    }
+}
+
+fn price(price: Option<f32>) -> String {
+    price.map(|price| format!("{price:.2}")).unwrap_or_default()
+}
+
+fn csv<W: Write>(report: &Report, out: W) -> anyhow::Result<()> {
+    let mut writer = csv::Writer::from_writer(out);
+    writer.write_record([
+        "Section",
+        "Name",
+        "Owned",
+        "Total",
+        "Missing",
+        "Percent In Decks",
+        "Price",
+    ])?;
+    for (section, bucket) in std::iter::once(("completion", &report.completion))
+        .chain(report.buckets.iter().map(|bucket| ("bucket", bucket)))
+    {
+        writer.write_record([
+            section,
+            &bucket.name,
+            &bucket.owned.to_string(),
+            &bucket.total.to_string(),
+            &(bucket.total - bucket.owned).to_string(),
+            "",
+            &format!("{:.2}", bucket.cost),
+        ])?;
+    }
+    for card in &report.missing {
+        writer.write_record([
+            "missing",
+            &card.name,
+            &card.owned.to_string(),
+            &card.target.to_string(),
+            &(usize::from(card.target) - card.owned).to_string(),
+            &card.percent_in_decks.to_string(),
+            &price(card.price),
+        ])?;
+    }
+    for entry in &report.wishlist {
+        writer.write_record([
+            "wishlist",
+            &entry.name,
+            "",
+            "",
+            &entry.missing.to_string(),
+            "",
+            &price(entry.price),
+        ])?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+fn markdown<W: Write>(report: &Report, mut out: W) -> anyhow::Result<()> {
+    let currency = report.currency;
+    let amount = |price: Option<f32>| {
+        price
+            .map(|price| currency.display(price).to_string())
+            .unwrap_or_else(|| "?".into())
+    };
+    let completion = &report.completion;
+    writeln!(out, "# {} | {}", report.format, report.board)?;
+    writeln!(out)?;
+    writeln!(
+        out,
+        "{}/{} copies, {} to go",
+        completion.owned,
+        completion.total,
+        currency.display(completion.cost)
+    )?;
+    writeln!(out)?;
+    writeln!(out, "## Statistics")?;
+    writeln!(out)?;
+    writeln!(out, "| Bucket | Owned | Total | To go |")?;
+    writeln!(out, "|---|---:|---:|---:|")?;
+    for bucket in &report.buckets {
+        writeln!(
+            out,
+            "| {} | {} | {} | {} |",
+            bucket.name,
+            bucket.owned,
+            bucket.total,
+            currency.display(bucket.cost)
+        )?;
+    }
+    writeln!(out)?;
+    writeln!(out, "## Missing")?;
+    writeln!(out)?;
+    writeln!(out, "| Card | Owned | Target | Play rate | Price |")?;
+    writeln!(out, "|---|---:|---:|---:|---:|")?;
+    for card in &report.missing {
+        writeln!(
+            out,
+            "| {} | {} | {} | {}% | {} |",
+            card.name,
+            card.owned,
+            card.target,
+            card.percent_in_decks,
+            amount(card.price)
+        )?;
+    }
+    writeln!(out)?;
+    writeln!(out, "## Wishlist")?;
+    writeln!(out)?;
+    for entry in &report.wishlist {
+        writeln!(
+            out,
+            "- {} {} ({} each)",
+            entry.missing,
+            entry.name,
+            amount(entry.price)
+        )?;
+    }
+    Ok(())
+}
+
+pub fn write<W: Write>(report: &Report, format: Format, mut out: W) -> anyhow::Result<()> {
+    match format {
+        Format::Json => {
+            serde_json::to_writer_pretty(&mut out, report)?;
+            writeln!(out)?;
+            Ok(())
+        }
+        Format::Csv => csv(report, out),
+        Format::Markdown => markdown(report, out),
+    }
+}
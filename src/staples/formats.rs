@@ -0,0 +1,150 @@
+use scryfall::{card::Game, format::Format};
+
+pub struct FormatInfo {
+    pub format: Format,
+    /// Names accepted on the command line.
+    pub names: &'static [&'static str],
+    /// Slug of the format in mtggoldfish's format-staples urls.
+    pub goldfish: Option<&'static str>,
+    /// Key of the format in mtgtop8's topcards form.
+    pub mtgtop8: Option<&'static str>,
+    /// Where the format is played, printings that don't exist there don't count.
+    pub game: Game,
+    /// Only common printings count.
+    pub commons_only: bool,
+}
+
+/// Every format either source has a metagame for, as long as scryfall knows the format.
+/// mtgtop8's Extended (EX), Peasant (PEA), Highlander (HIGH), Canadian Highlander (CHL), Block
+/// (BL), Limited (LI) and its pauper and MTGO commander metagames (EDHP, EDHM) are left out
+/// because [Format] has no variant for them.
+pub static FORMATS: [FormatInfo; 13] = [
+    FormatInfo {
+        format: Format::Pauper,
+        names: &["pauper"],
+        goldfish: Some("pauper"),
+        mtgtop8: Some("PAU"),
+        game: Game::Paper,
+        commons_only: true,
+    },
+    FormatInfo {
+        format: Format::Legacy,
+        names: &["legacy"],
+        goldfish: Some("legacy"),
+        mtgtop8: Some("LE"),
+        game: Game::Paper,
+        commons_only: false,
+    },
+    FormatInfo {
+        format: Format::Vintage,
+        names: &["vintage"],
+        goldfish: Some("vintage"),
+        mtgtop8: Some("VI"),
+        game: Game::Paper,
+        commons_only: false,
+    },
+    FormatInfo {
+        format: Format::Modern,
+        names: &["modern"],
+        goldfish: Some("modern"),
+        mtgtop8: Some("MO"),
+        game: Game::Paper,
+        commons_only: false,
+    },
+    FormatInfo {
+        format: Format::Pioneer,
+        names: &["pioneer"],
+        goldfish: Some("pioneer"),
+        mtgtop8: Some("PI"),
+        game: Game::Paper,
+        commons_only: false,
+    },
+    FormatInfo {
+        format: Format::Standard,
+        names: &["standard", "t2"],
+        goldfish: Some("standard"),
+        mtgtop8: Some("ST"),
+        game: Game::Paper,
+        commons_only: false,
+    },
+    FormatInfo {
+        format: Format::Premodern,
+        names: &["premodern"],
+        goldfish: Some("premodern"),
+        mtgtop8: Some("PREM"),
+        game: Game::Paper,
+        commons_only: false,
+    },
+    FormatInfo {
+        format: Format::Historic,
+        names: &["historic"],
+        goldfish: Some("historic"),
+        mtgtop8: Some("HI"),
+        game: Game::Arena,
+        commons_only: false,
+    },
+    FormatInfo {
+        format: Format::Explorer,
+        names: &["explorer"],
+        goldfish: Some("explorer"),
+        mtgtop8: Some("EXP"),
+        game: Game::Arena,
+        commons_only: false,
+    },
+    FormatInfo {
+        format: Format::Alchemy,
+        names: &["alchemy"],
+        goldfish: Some("alchemy"),
+        mtgtop8: Some("ALCH"),
+        game: Game::Arena,
+        commons_only: false,
+    },
+    FormatInfo {
+        format: Format::Penny,
+        names: &["penny", "penny dreadful"],
+        goldfish: Some("penny_dreadful"),
+        mtgtop8: None,
+        game: Game::Mtgo,
+        commons_only: false,
+    },
+    FormatInfo {
+        format: Format::Duel,
+        names: &["duel", "duel commander"],
+        goldfish: None,
+        mtgtop8: Some("EDH"),
+        game: Game::Paper,
+        commons_only: false,
+    },
+    // mtgtop8's only multiplayer commander metagame is the competitive one, so plain commander
+    // gets cEDH's staples too
+    FormatInfo {
+        format: Format::Commander,
+        names: &["commander", "edh", "cedh"],
+        goldfish: None,
+        mtgtop8: Some("cEDH"),
+        game: Game::Paper,
+        commons_only: false,
+    },
+];
+
+pub fn info(format: Format) -> Option<&'static FormatInfo> {
+    FORMATS.iter().find(|info| info.format == format)
+}
+
+/// Fuzzy matches a format name, `None` if nothing looks like it.
+pub fn parse(arg: &str) -> Option<Format> {
+    use fuzzy_matcher::skim::SkimMatcherV2;
+    use fuzzy_matcher::FuzzyMatcher;
+
+    let matcher = SkimMatcherV2::default();
+    FORMATS
+        .iter()
+        .flat_map(|info| info.names.iter().map(|name| (*name, info.format)))
+        .filter_map(|(format_str, format_enum)| {
+            matcher
+                .fuzzy_match(format_str, arg)
+                .map(|score| (score, format_enum))
+        })
+        .max_by_key(|(score, _)| *score)
+        .map(|(_, format)| format)
+}
@@ -1,24 +1,35 @@
-use anyhow::{bail, Context};
 use futures_util::{
+    future::LocalBoxFuture,
     stream::{self, FuturesUnordered},
-    StreamExt, TryStreamExt,
+    FutureExt, TryStreamExt,
 };
 use reqwest::Url;
 use scraper::{Html, Selector};
-use scryfall::{format::Format, Card};
+use scryfall::format::Format;
 
-use super::Metadata;
+use crate::card_name::CardName;
 
-fn urls_from_format(format: Format) -> anyhow::Result<[Url; 3]> {
-    let format = match format {
-        Format::Pauper => "pauper",
-        Format::Pioneer => "pioneer",
-        Format::Legacy => "legacy",
-        Format::Standard => "standard",
-        _ => bail!("{format} not supported"),
-    };
+use super::{source::StaplesSource, Metadata};
+
+pub struct Goldfish;
+
+impl StaplesSource for Goldfish {
+    fn name(&self) -> &'static str {
+        "goldfish"
+    }
+
+    fn fetch(
+        &self,
+        format: Format,
+    ) -> LocalBoxFuture<'static, anyhow::Result<Vec<(CardName, Metadata)>>> {
+        fetch(format).boxed_local()
+    }
+}
+
+fn urls_from_format(format: Format) -> Option<[Url; 3]> {
+    let format = super::formats::info(format)?.goldfish?;
 
-    Ok(["creatures", "spells", "lands"].map(|ty| {
+    Some(["creatures", "spells", "lands"].map(|ty| {
         Url::parse(&format!(
             "https://www.mtggoldfish.com/format-staples/{format}/full/{ty}"
         ))
@@ -26,7 +37,7 @@ fn urls_from_format(format: Format) -> anyhow::Result<[Url; 3]> {
     }))
 }
 
-pub async fn scrape(url: Url) -> anyhow::Result<Vec<anyhow::Result<(Card, Metadata)>>> {
+pub async fn scrape(url: Url) -> anyhow::Result<Vec<(CardName, Metadata)>> {
     let url_str = url.to_string();
     let html = reqwest::get(url).await?.text().await?;
     println!("{url_str} downloaded");
@@ -43,9 +54,9 @@ pub async fn scrape(url: Url) -> anyhow::Result<Vec<anyhow::Result<(Card, Metada
                     .map(|parent| parent.name());
                 parent != Some("thead")
             })
-            .map(|e| async move {
+            .map(|e| {
                 let mut values = e.text().map(str::trim).filter(|s| !s.is_empty()).skip(1);
-                let name = values.next().unwrap().into();
+                let name = CardName::from(values.next().unwrap().to_owned());
                 let percent_in_decks = values
                     .next()
                     .and_then(|s| s.trim_end_matches('%').parse().ok());
@@ -53,35 +64,32 @@ pub async fn scrape(url: Url) -> anyhow::Result<Vec<anyhow::Result<(Card, Metada
                     .next()
                     .and_then(|s| s.parse::<f32>().ok())
                     .map(|c| c.ceil() as u8);
-
-                let card = super::get_cached(name)
-                    .await
-                    .context("fetching from goldfish");
-                card.map(|card| (card, Metadata::new(percent_in_decks, num_copies)))
+                (name, Metadata::new(percent_in_decks, num_copies))
             })
-            .collect::<FuturesUnordered<_>>()
-            .into_stream()
-            .collect()
-            .await)
+            .collect())
     } else {
         eprintln!("WARN: could not find table for {url_str}");
         Ok(vec![])
     }
 }
 
-pub async fn fetch(format: Format) -> anyhow::Result<Vec<(Card, Option<Metadata>)>> {
-    urls_from_format(format)?
-        .map(|url| async move {
-            let url_str = url.to_string();
-            let s = scrape(url).await.map(stream::iter);
-            println!("{url_str} scraped");
-            s
-        })
-        .into_iter()
-        .collect::<FuturesUnordered<_>>()
-        .into_stream()
-        .try_flatten()
-        .map_ok(|(card, meta)| (card, Some(meta)))
-        .try_collect()
-        .await
+pub async fn fetch(format: Format) -> anyhow::Result<Vec<(CardName, Metadata)>> {
+    let Some(urls) = urls_from_format(format) else {
+        println!("[WARN] goldfish doesn't have staples for {format}");
+        return Ok(vec![]);
+    };
+    urls.map(|url| async move {
+        let url_str = url.to_string();
+        let s = scrape(url)
+            .await
+            .map(|cards| stream::iter(cards.into_iter().map(anyhow::Ok)));
+        println!("{url_str} scraped");
+        s
+    })
+    .into_iter()
+    .collect::<FuturesUnordered<_>>()
+    .into_stream()
+    .try_flatten()
+    .try_collect()
+    .await
 }
@@ -0,0 +1,88 @@
+use serde::{Deserialize, Serialize};
+
+use super::{source::Provenance, Board, BoardMetadata, Metadata};
+
+/// How the numbers of sources that list the same card are combined.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum MergePolicy {
+    /// Average the play rates by the sources' weights, and take the most copies any source lists
+    #[default]
+    Weighted,
+    /// Take the highest play rate and the most copies, regardless of weight
+    Max,
+    /// Take everything from the source with the highest weighted play rate
+    Highest,
+}
+
+/// Combines the metadata of every source that listed a card, `per_source` must not be empty.
+pub fn merge(policy: MergePolicy, per_source: &[(Provenance, Metadata)]) -> Metadata {
+    let board = |board: Board| {
+        let numbers = per_source
+            .iter()
+            .filter_map(|(provenance, metadata)| {
+                let numbers = match board {
+                    Board::Any => metadata.board(Board::Any),
+                    Board::Main => metadata.maindeck,
+                    Board::Side => metadata.sideboard,
+                };
+                Some((*provenance, numbers?))
+            })
+            .collect::<Vec<_>>();
+        (!numbers.is_empty()).then(|| merge_numbers(policy, &numbers))
+    };
+    let overall = board(Board::Any).expect("merging the metadata of no sources");
+    Metadata {
+        percent_in_decks: overall.percent_in_decks,
+        num_copies: overall.num_copies,
+        maindeck: board(Board::Main),
+        sideboard: board(Board::Side),
+    }
+}
+
+fn merge_numbers(policy: MergePolicy, per_source: &[(Provenance, BoardMetadata)]) -> BoardMetadata {
+    let max_copies = per_source
+        .iter()
+        .map(|(_, metadata)| metadata.num_copies)
+        .max()
+        .unwrap_or_default();
+    match policy {
+        MergePolicy::Weighted => {
+            let total_weight = per_source
+                .iter()
+                .map(|(provenance, _)| provenance.weight)
+                .sum::<f32>();
+            let percent_in_decks = if total_weight > 0.0 {
+                per_source
+                    .iter()
+                    .map(|(provenance, metadata)| metadata.percent_in_decks * provenance.weight)
+                    .sum::<f32>()
+                    / total_weight
+            } else {
+                per_source
+                    .iter()
+                    .map(|(_, metadata)| metadata.percent_in_decks)
+                    .sum::<f32>()
+                    / per_source.len() as f32
+            };
+            BoardMetadata {
+                percent_in_decks,
+                num_copies: max_copies,
+            }
+        }
+        MergePolicy::Max => BoardMetadata {
+            percent_in_decks: per_source
+                .iter()
+                .map(|(_, metadata)| metadata.percent_in_decks)
+                .fold(0.0, f32::max),
+            num_copies: max_copies,
+        },
+        MergePolicy::Highest => per_source
+            .iter()
+            .max_by(|(p_a, m_a), (p_b, m_b)| {
+                (m_a.percent_in_decks * p_a.weight).total_cmp(&(m_b.percent_in_decks * p_b.weight))
+            })
+            .map(|(_, metadata)| *metadata)
+            .unwrap_or_default(),
+    }
+}
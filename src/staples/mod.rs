@@ -1,25 +1,72 @@
+pub mod formats;
 pub mod goldfish;
+pub mod merge;
 pub mod mtgtop8;
+pub mod snapshots;
+pub mod source;
 
-use std::{collections::HashMap, io, path::PathBuf, sync::OnceLock};
+use std::collections::HashMap;
 
-use anyhow::{bail, Context};
-use futures_util::try_join;
+use anyhow::Context;
+use futures_util::{future::join_all, stream, StreamExt};
 use scryfall::{format::Format, Card};
-use tokio::{
-    sync::{OnceCell, RwLock, Semaphore},
-    task::LocalSet,
-};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
 
 use crate::{
+    cache::Cache,
     card_name::{CName, CardName},
-    PROG_NAME,
+    config::Config,
 };
 
-#[derive(Debug, Clone, Copy)]
+use self::source::{Provenance, SOURCES};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum Board {
+    /// Main decks and sideboards together
+    #[default]
+    Any,
+    /// Only main decks
+    Main,
+    /// Only sideboards
+    Side,
+}
+
+impl Board {
+    pub fn next(self) -> Self {
+        match self {
+            Self::Any => Self::Main,
+            Self::Main => Self::Side,
+            Self::Side => Self::Any,
+        }
+    }
+}
+
+impl std::fmt::Display for Board {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Any => "any board",
+            Self::Main => "maindeck",
+            Self::Side => "sideboard",
+        })
+    }
+}
+
+/// How much a card is played on one board.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct BoardMetadata {
+    pub percent_in_decks: f32,
+    pub num_copies: u8,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub struct Metadata {
     pub percent_in_decks: f32,
     pub num_copies: u8,
+    /// `None` both for cards that never make the maindeck and when the source doesn't split
+    /// boards.
+    pub maindeck: Option<BoardMetadata>,
+    pub sideboard: Option<BoardMetadata>,
 }
 
 impl Metadata {
@@ -27,86 +74,174 @@ impl Metadata {
         Self {
             percent_in_decks: percent_in_decks.unwrap_or(100.0),
             num_copies: num_copies.unwrap_or(4),
+            maindeck: None,
+            sideboard: None,
         }
     }
-}
 
-async fn get_cached(name: &CName) -> anyhow::Result<Card> {
-    fn cache_dir() -> &'static PathBuf {
-        static CACHE_DIR: OnceLock<PathBuf> = OnceLock::new();
-        CACHE_DIR.get_or_init(|| {
-            let mut cache_dir = dirs::cache_dir().unwrap();
-            cache_dir.push(PROG_NAME);
-            cache_dir.push("staples.json");
-            cache_dir
-        })
+    /// Marks these numbers as coming from a single board.
+    fn on_board(self, board: Board) -> Self {
+        let numbers = Some(BoardMetadata {
+            percent_in_decks: self.percent_in_decks,
+            num_copies: self.num_copies,
+        });
+        match board {
+            Board::Any => self,
+            Board::Main => Self {
+                maindeck: numbers,
+                ..self
+            },
+            Board::Side => Self {
+                sideboard: numbers,
+                ..self
+            },
+        }
     }
-    static STAPLE_CACHE: OnceCell<RwLock<HashMap<CardName, Card>>> = OnceCell::const_new();
-    static CONCURRENCY: Semaphore = Semaphore::const_new(8);
-    let _permit = CONCURRENCY.acquire().await.unwrap();
-
-    let cache = STAPLE_CACHE
-        .get_or_try_init(|| async {
-            let cards = match tokio::fs::read(cache_dir()).await {
-                Ok(cards) => cards,
-                Err(e) if e.kind() == io::ErrorKind::NotFound => {
-                    tokio::fs::create_dir_all(cache_dir().parent().unwrap()).await?;
-                    tokio::fs::File::create(cache_dir()).await?;
-                    vec![b'{', b'}']
-                }
-                Err(e) => bail!(e),
-            };
-            anyhow::Ok(RwLock::const_new(serde_json::from_slice(&cards)?))
-        })
-        .await?;
 
+    /// The numbers for one board. Cards whose sources didn't split boards count as maindeck
+    /// cards.
+    pub fn board(&self, board: Board) -> Option<BoardMetadata> {
+        let overall = BoardMetadata {
+            percent_in_decks: self.percent_in_decks,
+            num_copies: self.num_copies,
+        };
+        let split = self.maindeck.is_some() || self.sideboard.is_some();
+        match board {
+            Board::Any => Some(overall),
+            Board::Main if !split => Some(overall),
+            Board::Main => self.maindeck,
+            Board::Side => self.sideboard,
+        }
+    }
+
+    /// Folds in another listing of the same card by the same source, e.g. its sideboard numbers.
+    fn combine(&mut self, other: Self) {
+        fn most_played(
+            a: Option<BoardMetadata>,
+            b: Option<BoardMetadata>,
+        ) -> Option<BoardMetadata> {
+            match (a, b) {
+                (Some(a), Some(b)) if b.percent_in_decks > a.percent_in_decks => Some(b),
+                (a, b) => a.or(b),
+            }
+        }
+        self.percent_in_decks = self.percent_in_decks.max(other.percent_in_decks);
+        self.num_copies = self.num_copies.max(other.num_copies);
+        self.maindeck = most_played(self.maindeck, other.maindeck);
+        self.sideboard = most_played(self.sideboard, other.sideboard);
+    }
+}
+
+static CACHE: Cache<CardName, Card> = Cache::new("staples");
+/// The last list each source gave for each format, so xander can run offline.
+static LISTS: Cache<String, Vec<(CardName, Metadata)>> = Cache::volatile("staples-lists");
+
+pub async fn get_cached(name: &CName) -> anyhow::Result<Card> {
     let name = name.trimming_double_faced();
-    if let Some(card) = cache.read().await.get(name) {
+    if let Some(card) = crate::bulk::index()
+        .await?
+        .and_then(|index| index.card(name))
+    {
         return Ok(card.clone());
     }
-
-    let card = scryfall::Card::named(name)
+    CACHE
+        .get_or_fetch(name, || async {
+            let card = scryfall::Card::named(name)
+                .await
+                .with_context(|| format!("error fetching card with name '{name}'"))?;
+            let key: &CName = match card.card_faces.as_ref().and_then(|face| face.first()) {
+                Some(front_face) => front_face.name.as_str().into(),
+                None => card.name.as_str().into(),
+            };
+            let key = key.to_owned();
+            if !crate::cache::quiet() {
+                println!("{key} downloaded");
+            }
+            Ok((key, card))
+        })
         .await
-        .with_context(|| format!("error fetching card with name '{name}'"))?;
-    let mut cache = cache.write().await;
-    let name: &CName = match card.card_faces.as_ref().and_then(|face| face.first()) {
-        Some(front_face) => front_face.name.as_str().into(),
-        None => card.name.as_str().into(),
-    };
-    cache.insert(name.to_owned(), card.clone());
-    let cache = serde_json::to_vec::<HashMap<_, _>>(&*cache).unwrap();
-    tokio::fs::write(cache_dir(), cache).await?;
-    println!("{name} downloaded");
-    Ok(card)
 }
 
-pub async fn fetch(format: Format) -> anyhow::Result<Vec<(Card, Option<Metadata>)>> {
-    let local_set = LocalSet::new();
-    let (top8, goldfish) = try_join!(tokio::spawn(mtgtop8::fetch(format)), async {
-        Ok(local_set.run_until(goldfish::fetch(format)).await)
-    })?;
-    let (mut top8, goldfish) = (top8.unwrap(), goldfish.unwrap());
+#[derive(Debug)]
+pub struct Staple {
+    pub card: Card,
+    /// The combination of every source's numbers.
+    pub metadata: Metadata,
+    /// What each source that lists the card said about it.
+    pub sources: Vec<(Provenance, Metadata)>,
+}
+
+pub async fn fetch(format: Format, config: &Config) -> anyhow::Result<Vec<Staple>> {
+    let sources = SOURCES
+        .iter()
+        .map(|source| (source, config.source(source.name())))
+        .filter(|(_, source_config)| source_config.enabled)
+        .map(|(source, source_config)| async move {
+            let provenance = Provenance {
+                source: source.name(),
+                weight: source_config.weight,
+            };
+            let key = format!("{}/{format}", source.name());
+            let staples = LISTS
+                .get_or_fetch(&key, || async {
+                    Ok((key.clone(), source.fetch(format).await?))
+                })
+                .await;
+            (provenance, staples)
+        });
+
+    let mut names = vec![];
+    for (provenance, staples) in join_all(sources).await {
+        let staples =
+            staples.with_context(|| format!("fetching staples from {}", provenance.source))?;
+        println!("\t{}: {}", provenance.source, staples.len());
+        names.extend(
+            staples
+                .into_iter()
+                .map(|(name, metadata)| (name, metadata, provenance)),
+        );
+    }
+
+    let cards = stream::iter(names)
+        .map(|(name, metadata, provenance)| async move {
+            match get_cached(&name).await {
+                Ok(card) => Some((card, provenance, metadata)),
+                Err(e) => {
+                    println!(
+                        "[WARN] failed to fetch {name} from {}: {e}",
+                        provenance.source
+                    );
+                    None
+                }
+            }
+        })
+        .buffer_unordered(crate::cache::concurrency())
+        .filter_map(std::future::ready)
+        .collect::<Vec<_>>()
+        .await;
     println!("all cards downloaded");
-    println!("\ttop8: {}", top8.len());
-    println!("\tgold: {}", goldfish.len());
-    top8.extend(goldfish);
-    println!("\ttota: {}", top8.len());
-    top8.sort_unstable_by(|(card_a, meta_a), (card_b, meta_b)| {
-        card_a
-            .id
-            .cmp(&card_b.id)
-            .then_with(|| match (meta_a, meta_b) {
-                (Some(m_a), Some(m_b)) => m_a
-                    .percent_in_decks
-                    .total_cmp(&m_b.percent_in_decks)
-                    .reverse(),
-                (None, Some(_)) => std::cmp::Ordering::Greater,
-                (Some(_), None) => std::cmp::Ordering::Less,
-                _ => std::cmp::Ordering::Equal,
-            })
-    });
-    top8.dedup_by(|(a, _), (b, _)| a.id == b.id);
-    println!("all cards sorted {}", top8.len());
-
-    Ok(top8)
+    println!("\ttota: {}", cards.len());
+
+    let mut by_id = HashMap::<Uuid, (Card, Vec<(Provenance, Metadata)>)>::new();
+    for (card, provenance, metadata) in cards {
+        let (_, per_source) = by_id.entry(card.id).or_insert_with(|| (card, vec![]));
+        match per_source
+            .iter_mut()
+            .find(|(p, _)| p.source == provenance.source)
+        {
+            Some((_, m)) => m.combine(metadata),
+            None => per_source.push((provenance, metadata)),
+        }
+    }
+    let staples = by_id
+        .into_values()
+        .map(|(card, sources)| Staple {
+            card,
+            metadata: merge::merge(config.merge, &sources),
+            sources,
+        })
+        .collect::<Vec<_>>();
+    println!("all cards merged {}", staples.len());
+
+    Ok(staples)
 }
@@ -1,15 +1,29 @@
 use std::{collections::HashMap, iter::repeat};
 
-use anyhow::bail;
-use futures_util::{stream::FuturesUnordered, StreamExt, TryStreamExt};
+use futures_util::{future::LocalBoxFuture, stream::FuturesUnordered, FutureExt, TryStreamExt};
 use itertools::Itertools;
 use scraper::{ElementRef, Html, Selector};
-use scryfall::{format::Format, Card};
+use scryfall::format::Format;
 use serde::Serialize;
 
 use crate::card_name::CardName;
 
-use super::Metadata;
+use super::{source::StaplesSource, Metadata};
+
+pub struct Mtgtop8;
+
+impl StaplesSource for Mtgtop8 {
+    fn name(&self) -> &'static str {
+        "mtgtop8"
+    }
+
+    fn fetch(
+        &self,
+        format: Format,
+    ) -> LocalBoxFuture<'static, anyhow::Result<Vec<(CardName, Metadata)>>> {
+        fetch(format).boxed_local()
+    }
+}
 
 #[derive(Serialize, Debug, Clone, Copy)]
 #[serde(rename_all = "UPPERCASE")]
@@ -18,17 +32,20 @@ enum Board {
     Sb,
 }
 
-fn format_to_form_param(format: Format) -> anyhow::Result<&'static str> {
-    Ok(match format {
-        Format::Pauper => "PAU",
-        Format::Legacy => "LE",
-        Format::Pioneer => "PI",
-        Format::Standard => "ST",
-        _ => bail!("unsuported format: {format}"),
-    })
+impl From<Board> for super::Board {
+    fn from(board: Board) -> Self {
+        match board {
+            Board::Md => Self::Main,
+            Board::Sb => Self::Side,
+        }
+    }
 }
 
-pub async fn fetch(format: Format) -> anyhow::Result<Vec<(Card, Option<Metadata>)>> {
+pub async fn fetch(format: Format) -> anyhow::Result<Vec<(CardName, Metadata)>> {
+    let Some(form_format) = super::formats::info(format).and_then(|info| info.mtgtop8) else {
+        println!("[WARN] mtgtop8 doesn't have staples for {format}");
+        return Ok(vec![]);
+    };
     let url = "https://mtgtop8.com/topcards";
     let static_fields = &HashMap::from_iter([
         ("data", "1"),
@@ -70,12 +87,11 @@ pub async fn fetch(format: Format) -> anyhow::Result<Vec<(Card, Option<Metadata>
     let cards = ((1..=16).zip(repeat(Board::Md)))
         .chain((1..=16).zip(repeat(Board::Sb)))
         .map(|(page, board)| async move {
-            println!("=> downloading page {page:02} of mtgtop8 ({board:?})");
             let text = client
                 .post(url)
                 .form(&Form {
                     current_page: page.to_string(),
-                    format: format_to_form_param(format)?,
+                    format: form_format,
                     maindeck: board,
                     static_fields,
                 })
@@ -84,8 +100,6 @@ pub async fn fetch(format: Format) -> anyhow::Result<Vec<(Card, Option<Metadata>
                 .text()
                 .await?;
 
-            println!("xxx downloaded page {page:02} of mtgtop8 ({board:?})");
-
             let doc = Html::parse_document(&text);
             let selector = Selector::parse(r#"td[class="L14"]"#).unwrap();
             let page_cards = doc
@@ -106,13 +120,12 @@ pub async fn fetch(format: Format) -> anyhow::Result<Vec<(Card, Option<Metadata>
                     let name = CardName::from(name.text().collect::<String>());
                     let percent = text_to_f(&percent);
                     let num_copies = text_to_f(&number_in_decks).map(|n| n.ceil() as u8);
-                    (page, name, Metadata::new(percent, num_copies))
+                    (
+                        name,
+                        Metadata::new(percent, num_copies).on_board(board.into()),
+                    )
                 })
                 .collect::<Vec<_>>();
-            println!(
-                "<===== scraped page {page:02} of mtgtop8, found {} cards",
-                page_cards.len(),
-            );
             anyhow::Ok(page_cards)
         })
         .collect::<FuturesUnordered<_>>()
@@ -120,19 +133,5 @@ pub async fn fetch(format: Format) -> anyhow::Result<Vec<(Card, Option<Metadata>
         .try_collect::<Vec<Vec<_>>>()
         .await?;
 
-    Ok(futures_util::stream::iter(cards.into_iter().flatten().map(
-        |(page, card, percent)| async move {
-            match super::get_cached(&card).await {
-                Ok(card) => Some((card, Some(percent))),
-                Err(e) => {
-                    println!("[WARN] failed to fetch {card} from page {page}: {e}");
-                    None
-                }
-            }
-        },
-    ))
-    .buffer_unordered(usize::MAX)
-    .filter_map(std::future::ready)
-    .collect::<Vec<_>>()
-    .await)
+    Ok(cards.into_iter().flatten().collect())
 }
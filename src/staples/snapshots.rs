@@ -0,0 +1,171 @@
+use std::{collections::HashMap, fmt, io, path::PathBuf};
+
+use anyhow::Context;
+use chrono::{DateTime, Local, NaiveDate, Utc};
+use scryfall::format::Format;
+use serde::{Deserialize, Serialize};
+
+use crate::{card_name::CardName, persist};
+
+use super::{Metadata, Staple};
+
+fn snapshots_dir(format: Format) -> PathBuf {
+    let mut path = crate::config::data_dir().join("snapshots");
+    path.push(format.to_string());
+    path
+}
+
+/// The staples of a format as they were on one day.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub taken_at: DateTime<Utc>,
+    pub cards: HashMap<CardName, Metadata>,
+}
+
+/// Saves today's snapshot of `format`, replacing any taken earlier today.
+pub async fn save(format: Format, staples: &[Staple]) -> anyhow::Result<()> {
+    let snapshot = Snapshot {
+        taken_at: Utc::now(),
+        cards: staples
+            .iter()
+            .map(|staple| (CardName::from(staple.card.name.clone()), staple.metadata))
+            .collect(),
+    };
+    let dir = snapshots_dir(format);
+    tokio::fs::create_dir_all(&dir).await?;
+    let path = dir.join(format!("{}.json", Local::now().date_naive()));
+    persist::atomic_write(&path, &serde_json::to_vec(&snapshot)?).await
+}
+
+/// The days there are snapshots of `format` for, oldest first.
+async fn dates(format: Format) -> anyhow::Result<Vec<NaiveDate>> {
+    let mut entries = match tokio::fs::read_dir(snapshots_dir(format)).await {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(vec![]),
+        Err(e) => return Err(e.into()),
+    };
+    let mut dates = vec![];
+    while let Some(entry) = entries.next_entry().await? {
+        let path = entry.path();
+        if let Some(date) = path
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .filter(|_| path.extension().is_some_and(|ext| ext == "json"))
+            .and_then(|stem| stem.parse().ok())
+        {
+            dates.push(date);
+        }
+    }
+    dates.sort_unstable();
+    Ok(dates)
+}
+
+async fn load(format: Format, date: NaiveDate) -> anyhow::Result<Snapshot> {
+    let path = snapshots_dir(format).join(format!("{date}.json"));
+    let snapshot = tokio::fs::read(&path)
+        .await
+        .with_context(|| format!("reading {}", path.display()))?;
+    Ok(serde_json::from_slice(&snapshot)?)
+}
+
+#[derive(Debug)]
+pub struct Trend {
+    pub name: CardName,
+    /// `None` if the card wasn't a staple back then.
+    pub before: Option<f32>,
+    /// `None` if the card is no longer a staple.
+    pub after: Option<f32>,
+}
+
+impl Trend {
+    pub fn delta(&self) -> f32 {
+        self.after.unwrap_or_default() - self.before.unwrap_or_default()
+    }
+}
+
+impl fmt::Display for Trend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let percent = |p: Option<f32>| match p {
+            Some(p) => format!("{p:.1}%"),
+            None => "-".into(),
+        };
+        write!(
+            f,
+            "{:+.1}% {} ({} -> {})",
+            self.delta(),
+            self.name,
+            percent(self.before),
+            percent(self.after)
+        )
+    }
+}
+
+#[derive(Debug)]
+pub struct Trends {
+    pub since: NaiveDate,
+    pub until: NaiveDate,
+    /// Every card whose play rate changed, biggest rise first.
+    pub cards: Vec<Trend>,
+}
+
+impl Trends {
+    pub fn rising(&self) -> impl Iterator<Item = &Trend> {
+        self.cards.iter().take_while(|trend| trend.delta() > 0.0)
+    }
+
+    pub fn falling(&self) -> impl Iterator<Item = &Trend> {
+        self.cards
+            .iter()
+            .rev()
+            .take_while(|trend| trend.delta() < 0.0)
+    }
+}
+
+/// Compares the newest snapshot of `format` with the one before it, or with the last one taken
+/// on or before `since`. `None` if there aren't two snapshots to compare.
+pub async fn trends(format: Format, since: Option<NaiveDate>) -> anyhow::Result<Option<Trends>> {
+    let dates = dates(format).await?;
+    let Some((&until, older)) = dates.split_last() else {
+        return Ok(None);
+    };
+    let since = match since {
+        Some(since) => older
+            .iter()
+            .rev()
+            .find(|date| **date <= since)
+            .or(older.first()),
+        None => older.last(),
+    };
+    let Some(&since) = since else {
+        return Ok(None);
+    };
+
+    let (before, after) = (load(format, since).await?, load(format, until).await?);
+    let mut cards = after
+        .cards
+        .iter()
+        .map(|(name, metadata)| Trend {
+            name: name.clone(),
+            before: before.cards.get(name).map(|m| m.percent_in_decks),
+            after: Some(metadata.percent_in_decks),
+        })
+        .chain(
+            before
+                .cards
+                .iter()
+                .filter(|(name, _)| !after.cards.contains_key(*name))
+                .map(|(name, metadata)| Trend {
+                    name: name.clone(),
+                    before: Some(metadata.percent_in_decks),
+                    after: None,
+                }),
+        )
+        .filter(|trend| trend.delta() != 0.0)
+        .collect::<Vec<_>>();
+    cards.sort_by(|a, b| a.delta().total_cmp(&b.delta()).reverse());
+    Ok(Some(Trends {
+        since,
+        until,
+        cards,
+    }))
+}
@@ -0,0 +1,35 @@
+use futures_util::future::LocalBoxFuture;
+use scryfall::format::Format;
+
+use crate::card_name::CardName;
+
+use super::{goldfish::Goldfish, mtgtop8::Mtgtop8, Metadata};
+
+/// A site that publishes which cards are played in a format.
+pub trait StaplesSource: Sync {
+    /// The name used for this source on the command line and in the config file.
+    fn name(&self) -> &'static str;
+
+    /// Scrapes the staples of `format`, an empty list if the site doesn't cover it.
+    fn fetch(
+        &self,
+        format: Format,
+    ) -> LocalBoxFuture<'static, anyhow::Result<Vec<(CardName, Metadata)>>>;
+}
+
+/// Every known source, adding a site only requires adding it here.
+pub static SOURCES: [&dyn StaplesSource; 2] = [&Mtgtop8, &Goldfish];
+
+pub fn find(name: &str) -> Option<&'static dyn StaplesSource> {
+    SOURCES
+        .iter()
+        .copied()
+        .find(|source| source.name().eq_ignore_ascii_case(name))
+}
+
+/// Where a staple's metadata came from.
+#[derive(Debug, Clone, Copy)]
+pub struct Provenance {
+    pub source: &'static str,
+    pub weight: f32,
+}
@@ -0,0 +1,172 @@
+use scryfall::card::Color;
+use serde::Serialize;
+use static_assertions::const_assert;
+
+use crate::{
+    checklist::{Checklist, ChecklistCard},
+    prices::Currency,
+    staples::Board,
+};
+
+#[derive(Default, Debug, Clone, Copy, Serialize)]
+pub struct Progress {
+    pub owned: u16,
+    pub total: u16,
+    /// What the missing copies cost, going by each card's cheapest printing.
+    pub cost: f32,
+}
+
+#[derive(Default, Debug)]
+pub struct Stats {
+    pub everything: Progress,
+    pub top_20: Progress,
+    pub top_50: Progress,
+    pub top_150: Progress,
+    pub top_20_by_color: [Progress; 5],
+    pub top_10_colorless: Progress,
+    pub top_20_multicolor: Progress,
+    pub top_10_lands: Progress,
+}
+
+impl Stats {
+    /// Every bucket with the name it's shown under.
+    pub fn buckets(&self) -> Vec<(String, Progress)> {
+        let mut buckets = vec![
+            ("Whole checklist".to_owned(), self.everything),
+            ("Top 20".to_owned(), self.top_20),
+            ("Top 50".to_owned(), self.top_50),
+            ("Top 150".to_owned(), self.top_150),
+        ];
+        buckets.extend(
+            WUBRG
+                .iter()
+                .zip(self.top_20_by_color)
+                .map(|(color, progress)| (format!("Top 20 {color} cards"), progress)),
+        );
+        buckets.extend([
+            ("Top 10 colorless".to_owned(), self.top_10_colorless),
+            ("Top 20 multicolor".to_owned(), self.top_20_multicolor),
+            ("Top 10 land".to_owned(), self.top_10_lands),
+        ]);
+        buckets
+    }
+}
+
+pub const WUBRG: [Color; 5] = [
+    Color::White,
+    Color::Blue,
+    Color::Black,
+    Color::Red,
+    Color::Green,
+];
+
+/// Where a mono colored card is counted, its color's position in [WUBRG].
+fn color_index(color: Color) -> usize {
+    (color as u8).trailing_zeros() as usize
+}
+
+pub fn calculate(checklist: &Checklist, board: Board, currency: Currency) -> Stats {
+    let mut top_cards = Vec::with_capacity(150);
+
+    const_assert!((Color::White as u8).trailing_zeros() == 0);
+    const_assert!((Color::Blue as u8).trailing_zeros() == 1);
+    const_assert!((Color::Black as u8).trailing_zeros() == 2);
+    const_assert!((Color::Red as u8).trailing_zeros() == 3);
+    const_assert!((Color::Green as u8).trailing_zeros() == 4);
+    const COLORLESS: usize = 5;
+    const MULTICOLOR: usize = 6;
+    const LAND: usize = 7;
+    let mut counters = [0_u16; LAND + 1];
+
+    fn counters_full(counters: &[u16; 8]) -> bool {
+        WUBRG.into_iter().all(|c| counters[color_index(c)] >= 20)
+            && counters[5] >= 20
+            && counters[6] >= 10
+    }
+    let percent = |c: &ChecklistCard| {
+        c.numbers(board)
+            .map(|numbers| numbers.percent_in_decks)
+            .unwrap_or_default()
+    };
+    let mut cards = checklist.ignoring_collection();
+    cards.retain(|c| c.numbers(board).is_some());
+    cards.sort_by(|a, b| percent(a).total_cmp(&percent(b)).reverse());
+    let mut iter = cards.iter().copied();
+    while !counters_full(&counters) {
+        let Some(c) = iter.next() else {
+            break;
+        };
+        let card = &c.card;
+        let index = match card.colors.as_deref() {
+            _ if card.type_line.as_ref().is_some_and(|s| s.contains("Land")) => LAND,
+            None | Some(&[]) => COLORLESS,
+            Some(&[c]) => color_index(c),
+            Some(&[_, ..]) => MULTICOLOR,
+        };
+        counters[index] += 1;
+        top_cards.push(c);
+    }
+
+    return Stats {
+        everything: top(&cards, board, currency, usize::MAX, |_| true),
+        top_20: top(&top_cards, board, currency, 20, |_| true),
+        top_50: top(&top_cards, board, currency, 50, |_| true),
+        top_150: top(&top_cards, board, currency, 150, |_| true),
+        top_20_by_color: WUBRG.map(|color| {
+            top(&top_cards, board, currency, 20, |c| {
+                c.card.colors.as_ref().is_some_and(|c| c == &[color])
+            })
+        }),
+        top_10_colorless: top(&top_cards, board, currency, 10, |c| {
+            c.card.colors.as_ref().map(|s| s.is_empty()).unwrap_or(true)
+        }),
+        top_20_multicolor: top(&top_cards, board, currency, 20, |c| {
+            c.card.colors.as_ref().is_some_and(|s| s.len() > 1)
+        }),
+        top_10_lands: top(&top_cards, board, currency, 10, |c| {
+            c.card
+                .type_line
+                .as_ref()
+                .is_some_and(|t| t.contains("Land"))
+        }),
+    };
+
+    fn top<F: Fn(&ChecklistCard) -> bool>(
+        cards: &[&ChecklistCard],
+        board: Board,
+        currency: Currency,
+        count: usize,
+        f: F,
+    ) -> Progress {
+        cards
+            .iter()
+            .filter(|x| f(x))
+            .take(count)
+            .fold(Progress::default(), |mut prog, c| {
+                let num_copies = c
+                    .numbers(board)
+                    .map(|numbers| numbers.num_copies)
+                    .unwrap_or_default()
+                    .into();
+                let relevant_owned = u16::min(c.eligible_versions() as u16, num_copies);
+
+                prog.owned += relevant_owned;
+                prog.total += num_copies;
+                prog.cost += f32::from(num_copies - relevant_owned)
+                    * c.cheapest_price(currency).unwrap_or_default();
+                prog
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // this used to be `trailing_ones`, which counted white cards as blue and every other color
+    // as white
+    #[test]
+    fn each_color_has_its_own_counter() {
+        assert_eq!(WUBRG.map(color_index), [0, 1, 2, 3, 4]);
+    }
+}
@@ -5,17 +5,28 @@ use cursive::{
     theme::{BaseColor, Color, ColorStyle, ColorType},
     utils::{span::SpannedString, Counter},
     view::{Nameable, Resizable, Scrollable},
-    views::{Dialog, EditView, LinearLayout, OnEventView, ProgressBar, ScrollView, SelectView},
+    views::{
+        Dialog, EditView, LinearLayout, ListView, OnEventView, ProgressBar, ScrollView, SelectView,
+    },
     Cursive, View,
 };
 use scryfall::set::SetCode;
 
 use crate::{
-    card_name::CardName,
-    checklist::{Checklist, ChecklistCard},
+    card_name::{CName, CardName},
+    checklist::{Checklist, ChecklistCard, Set},
+    collection::{journal::Change, Condition, Finish, Version},
+    overrides::Override,
+    prices::Currency,
 };
 
-use super::{background, show, vim::ViewExt, CursiveExt, MAIN_LAYOUT};
+use super::{
+    background,
+    decks::{collection_changed, copies_dialog},
+    information_dialog, show,
+    vim::ViewExt,
+    CursiveExt, MAIN_LAYOUT,
+};
 
 pub const CARD_LIST: &str = "card-list";
 pub const CARD_LIST_SCROLL_VIEW: &str = "card-list-scroll-view";
@@ -52,25 +63,7 @@ fn get_selected_card_name(s: &mut Cursive) -> CardName {
     .expect(CARD_LIST)
 }
 
-fn add_collected_version(s: &mut Cursive, version: SetCode) {
-    let collection = s.data().collection.clone();
-    let (index, len) = s
-        .call_on_name::<CardList, _, _>(CARD_LIST, |card_list| {
-            let index = card_list.selected_id().unwrap();
-            let len = {
-                let card = card_list
-                    .get_item_mut(index)
-                    .map(|(_, index)| &collection[*index])
-                    .unwrap();
-                card.add_version(version)
-            };
-            (index, len)
-        })
-        .expect(CARD_LIST);
-    s.call_on_name::<SelectView<SetCode>, _, _>(VERSION_VIEWER, |set_codes| {
-        set_codes.add_item(version.to_string(), version);
-    })
-    .expect(VERSION_VIEWER);
+fn set_progress(s: &mut Cursive, index: usize, len: usize) {
     s.call_on_name::<LinearLayout, _, _>(PROGRESS_VIEWER, |collection_viewer| {
         let progress = collection_viewer
             .get_child_mut(index)
@@ -82,49 +75,280 @@ fn add_collected_version(s: &mut Cursive, version: SetCode) {
     .expect(PROGRESS_VIEWER);
 }
 
-fn del_collected_version(s: &mut Cursive, version: SetCode) {
+fn add_collected_version(s: &mut Cursive, version: Version) {
+    let card = get_selected_card_name(s).trimming_double_faced();
+    collection_changed(
+        s,
+        &[Change::Add {
+            card,
+            version: version.clone(),
+        }],
+    );
     let collection = s.data().collection.clone();
     let (index, len) = s
         .call_on_name::<CardList, _, _>(CARD_LIST, |card_list| {
             let index = card_list.selected_id().unwrap();
-            let len = {
-                let (_, index) = card_list.get_item_mut(index).unwrap();
-                collection[*index].remove_version(version)
-            };
-            (index, len)
+            let card = card_list
+                .get_item_mut(index)
+                .map(|(_, index)| &collection[*index])
+                .unwrap();
+            card.add_version(version.clone());
+            (index, card.eligible_versions())
         })
         .expect(CARD_LIST);
-    s.call_on_name::<SelectView<SetCode>, _, _>(VERSION_VIEWER, |set_codes| {
-        let selected = set_codes.selected_id().unwrap();
-        set_codes.remove_item(selected);
+    s.call_on_name::<SelectView<Version>, _, _>(VERSION_VIEWER, |versions| {
+        versions.add_item(version.to_string(), version);
     })
     .expect(VERSION_VIEWER);
-    s.call_on_name::<LinearLayout, _, _>(PROGRESS_VIEWER, |collection_viewer| {
-        let progress = collection_viewer
-            .get_child_mut(index)
-            .unwrap()
-            .downcast_mut::<ProgressBar>()
-            .unwrap();
-        progress.set_value(len);
+    set_progress(s, index, len);
+}
+
+fn del_collected_version(s: &mut Cursive, version: &Version) {
+    let card = get_selected_card_name(s).trimming_double_faced();
+    collection_changed(
+        s,
+        &[Change::Del {
+            card,
+            version: version.clone(),
+        }],
+    );
+    let collection = s.data().collection.clone();
+    let (index, len) = s
+        .call_on_name::<CardList, _, _>(CARD_LIST, |card_list| {
+            let index = card_list.selected_id().unwrap();
+            let card = card_list
+                .get_item_mut(index)
+                .map(|(_, index)| &collection[*index])
+                .unwrap();
+            card.remove_version(version);
+            (index, card.eligible_versions())
+        })
+        .expect(CARD_LIST);
+    s.call_on_name::<SelectView<Version>, _, _>(VERSION_VIEWER, |versions| {
+        let selected = versions.selected_id().unwrap();
+        versions.remove_item(selected);
     })
-    .expect(PROGRESS_VIEWER);
+    .expect(VERSION_VIEWER);
+    set_progress(s, index, len);
+}
+
+fn update_collected_version(s: &mut Cursive, old: &Version, new: Version) {
+    let card = get_selected_card_name(s).trimming_double_faced();
+    collection_changed(
+        s,
+        &[Change::Update {
+            card,
+            from: old.clone(),
+            to: new.clone(),
+        }],
+    );
+    let collection = s.data().collection.clone();
+    s.call_on_name::<CardList, _, _>(CARD_LIST, |card_list| {
+        let index = card_list.selected_id().unwrap();
+        let (_, index) = card_list.get_item_mut(index).unwrap();
+        collection[*index].update_version(old, new.clone());
+    })
+    .expect(CARD_LIST);
+    s.call_on_name::<SelectView<Version>, _, _>(VERSION_VIEWER, |versions| {
+        let selected = versions.selected_id().unwrap();
+        if let Some((label, version)) = versions.get_item_mut(selected) {
+            *label = new.to_string().into();
+            *version = new;
+        }
+    })
+    .expect(VERSION_VIEWER);
+}
+
+/// Refills every bar, e.g. after a deck was built and its copies stopped counting.
+pub fn refresh_progress(s: &mut Cursive) {
+    let collection = s.data().collection.clone();
+    let rows = s
+        .call_on_name::<CardList, _, _>(CARD_LIST, |card_list| {
+            card_list
+                .iter()
+                .map(|(_, index)| collection[*index].eligible_versions())
+                .collect::<Vec<_>>()
+        })
+        .expect(CARD_LIST);
+    for (position, len) in rows.into_iter().enumerate() {
+        set_progress(s, position, len);
+    }
+}
+
+/// Mirrors changes made to the collection file, e.g. by an undo, in the card list.
+pub fn apply_changes(s: &mut Cursive, changes: &[Change]) {
+    collection_changed(s, changes);
+    let collection = s.data().collection.clone();
+    for change in changes {
+        let Some(index) = collection.iter().position(|card| {
+            let name: &CName = card.card.name.as_str().into();
+            name.trimming_double_faced() == change.card().as_slice()
+        }) else {
+            continue;
+        };
+        let card = &collection[index];
+        match change {
+            Change::Add { version, .. } => {
+                card.add_version(version.clone());
+            }
+            Change::Del { version, .. } => {
+                card.remove_version(version);
+            }
+            Change::Update { from, to, .. } => card.update_version(from, to.clone()),
+        }
+        let len = card.eligible_versions();
+        let position = s
+            .call_on_name::<CardList, _, _>(CARD_LIST, |card_list| {
+                card_list.iter().position(|(_, i)| *i == index)
+            })
+            .expect(CARD_LIST);
+        if let Some(position) = position {
+            set_progress(s, position, len);
+        }
+    }
+}
+
+const COLLECTOR_NUMBER_EDITOR: &str = "collector-number-editor";
+const FINISH_PICKER: &str = "finish-picker";
+const CONDITION_PICKER: &str = "condition-picker";
+const LANGUAGE_EDITOR: &str = "language-editor";
+
+fn version_form(version: &Version) -> impl View {
+    let finish = Finish::ALL
+        .iter()
+        .position(|f| *f == version.finish)
+        .unwrap_or_default();
+    let condition = Condition::ALL
+        .iter()
+        .position(|c| *c == version.condition)
+        .unwrap_or_default();
+    ListView::new()
+        .child(
+            "Collector number",
+            EditView::new()
+                .content(version.collector_number.clone().unwrap_or_default())
+                .with_name(COLLECTOR_NUMBER_EDITOR)
+                .min_width(8),
+        )
+        .child(
+            "Finish",
+            SelectView::new()
+                .popup()
+                .with_all(Finish::ALL.map(|f| (f.to_string(), f)))
+                .selected(finish)
+                .with_name(FINISH_PICKER),
+        )
+        .child(
+            "Condition",
+            SelectView::new()
+                .popup()
+                .with_all(Condition::ALL.map(|c| (c.to_string(), c)))
+                .selected(condition)
+                .with_name(CONDITION_PICKER),
+        )
+        .child(
+            "Language",
+            EditView::new()
+                .content(version.language.clone())
+                .with_name(LANGUAGE_EDITOR)
+                .min_width(8),
+        )
+}
+
+fn read_version_form(s: &mut Cursive, set: SetCode) -> Version {
+    let collector_number = s
+        .call_on_name::<EditView, _, _>(COLLECTOR_NUMBER_EDITOR, |view| view.get_content())
+        .expect(COLLECTOR_NUMBER_EDITOR);
+    let finish = s
+        .call_on_name::<SelectView<Finish>, _, _>(FINISH_PICKER, |view| view.selection())
+        .expect(FINISH_PICKER)
+        .map(|f| *f)
+        .unwrap_or_default();
+    let condition = s
+        .call_on_name::<SelectView<Condition>, _, _>(CONDITION_PICKER, |view| view.selection())
+        .expect(CONDITION_PICKER)
+        .map(|c| *c)
+        .unwrap_or_default();
+    let language = s
+        .call_on_name::<EditView, _, _>(LANGUAGE_EDITOR, |view| view.get_content())
+        .expect(LANGUAGE_EDITOR);
+
+    let mut version = Version::new(set);
+    version.collector_number = Some(collector_number.trim())
+        .filter(|n| !n.is_empty())
+        .map(ToOwned::to_owned);
+    version.finish = finish;
+    version.condition = condition;
+    if !language.trim().is_empty() {
+        version.language = language.trim().to_lowercase();
+    }
+    version
+}
+
+fn new_version_dialog(set: &Set) -> impl View {
+    let mut version = Version::new(set.code);
+    version.collector_number = set.collector_number.clone();
+    Dialog::new()
+        .title(format!("Add {}", set.name))
+        .content(version_form(&version))
+        .button("Add", move |s| {
+            let version = read_version_form(s, version.set);
+            let selected = get_selected_card_name(s);
+            background(
+                s.data().tx_error.clone(),
+                crate::collection::add_to_collection(selected, version.clone()),
+            );
+            add_collected_version(s, version);
+            s.pop_layer();
+        })
+        .dismiss_button("Cancel")
+        .esq_to_quit()
 }
 
-fn edit_collected_card_dialog(card: &ChecklistCard) -> impl View {
+fn edit_version_dialog(version: Version) -> impl View {
+    Dialog::new()
+        .title(format!("Edit {}", version.set))
+        .content(version_form(&version))
+        .button("Save", {
+            let version = version.clone();
+            move |s| {
+                let new = read_version_form(s, version.set);
+                let selected = get_selected_card_name(s);
+                background(
+                    s.data().tx_error.clone(),
+                    crate::collection::update_in_collection(selected, version.clone(), new.clone()),
+                );
+                update_collected_version(s, &version, new);
+                s.pop_layer();
+            }
+        })
+        .button("Delete", move |s| {
+            let selected = get_selected_card_name(s);
+            background(
+                s.data().tx_error.clone(),
+                crate::collection::del_from_collection(selected, version.clone()),
+            );
+            del_collected_version(s, &version);
+            s.pop_layer();
+        })
+        .dismiss_button("Cancel")
+        .esq_to_quit()
+}
+
+fn edit_collected_card_dialog(card: &ChecklistCard, currency: Currency) -> impl View {
     let mut versions_view = SelectView::new();
 
     for version in card.owned_versions().iter() {
-        versions_view.add_item(version.to_string(), *version);
+        let label = if card.counts(version) {
+            version.to_string()
+        } else {
+            format!("{version} (doesn't count towards the format)")
+        };
+        versions_view.add_item(label, version.clone());
     }
 
-    versions_view.set_on_submit(|s, item| {
-        let selected = get_selected_card_name(s);
-        background(
-            s.data().tx_error.clone(),
-            crate::collection::del_from_collection(selected, *item),
-        );
-        del_collected_version(s, *item)
-    });
+    versions_view
+        .set_on_submit(|s, version: &Version| s.add_layer(edit_version_dialog(version.clone())));
 
     let printings = card.printings.clone();
 
@@ -137,16 +361,18 @@ fn edit_collected_card_dialog(card: &ChecklistCard) -> impl View {
         .button("Add", move |s| {
             let mut set_picker = SelectView::new();
             for set in &printings {
-                set_picker.add_item(&set.name, set.code);
+                let mut label = match &set.collector_number {
+                    Some(number) => format!("{} #{number}", set.name),
+                    None => set.name.clone(),
+                };
+                if let Some(price) = set.prices.get(currency) {
+                    label += &format!(" {}", currency.display(price));
+                }
+                set_picker.add_item(label, set.clone());
             }
-            set_picker.set_on_submit(|s, &set| {
-                let selected = get_selected_card_name(s);
-                background(
-                    s.data().tx_error.clone(),
-                    crate::collection::add_to_collection(selected, set),
-                );
-                add_collected_version(s, set);
+            set_picker.set_on_submit(|s, set: &Set| {
                 s.pop_layer();
+                s.add_layer(new_version_dialog(set));
             });
             s.add_layer(
                 Dialog::new()
@@ -164,7 +390,140 @@ pub enum SortMode {
     NoCollection,
 }
 
-pub fn collection_viewer(collection: Rc<Checklist>, sort_mode: SortMode) -> impl View {
+/// The maindeck and sideboard numbers, when the sources split them.
+fn boards_label(card: &ChecklistCard) -> String {
+    let mut label = String::new();
+    for (name, numbers) in [
+        ("main", card.metadata.maindeck),
+        ("side", card.metadata.sideboard),
+    ] {
+        if let Some(numbers) = numbers {
+            label += &format!(
+                " {name} {}%x{}",
+                numbers.percent_in_decks, numbers.num_copies
+            );
+        }
+    }
+    label
+}
+
+/// Where a card's numbers came from, with each source's own play rate when several list it.
+fn sources_label(card: &ChecklistCard) -> String {
+    match card.sources.as_slice() {
+        [(provenance, _)] => provenance.source.to_owned(),
+        sources => sources
+            .iter()
+            .map(|(provenance, metadata)| {
+                format!("{} {}%", provenance.source, metadata.percent_in_decks)
+            })
+            .collect::<Vec<_>>()
+            .join(", "),
+    }
+}
+
+/// The numbers next to a card's progress bar.
+fn progress_label(
+    card: &ChecklistCard,
+    currency: Currency,
+) -> impl Fn(usize, (usize, usize)) -> String {
+    let metadata = card.metadata;
+    let overrides = card.overrides();
+    let num_copies = overrides.target.unwrap_or(metadata.num_copies);
+    let flags = [
+        (overrides.pinned, "pinned"),
+        (overrides.ignored, "ignored"),
+        (overrides.target.is_some(), "custom target"),
+    ]
+    .into_iter()
+    .filter(|(set, _)| *set)
+    .map(|(_, flag)| flag)
+    .collect::<Vec<_>>();
+    let flags = if flags.is_empty() {
+        String::new()
+    } else {
+        format!(" [{}]", flags.join(", "))
+    };
+    let boards = boards_label(card);
+    let sources = sources_label(card);
+    let price = card
+        .cheapest_price(currency)
+        .map(|price| format!(" from {}", currency.display(price)))
+        .unwrap_or_default();
+    move |value, _| {
+        format!(
+            "{value}/{num_copies}{flags} ({}%{boards} {sources}){price}",
+            metadata.percent_in_decks
+        )
+    }
+}
+
+/// Changes the selected card's overrides and saves them in the background.
+fn edit_overrides<F: FnOnce(&mut Override)>(s: &mut Cursive, edit: F) {
+    let collection = s.data().collection.clone();
+    let selected = s
+        .call_on_name::<CardList, _, _>(CARD_LIST, |card_list| {
+            let position = card_list.selected_id()?;
+            card_list
+                .get_item(position)
+                .map(|(_, index)| (position, *index))
+        })
+        .expect(CARD_LIST);
+    let Some((position, index)) = selected else {
+        return;
+    };
+    let card = &collection[index];
+    let mut overrides = card.overrides();
+    edit(&mut overrides);
+    card.set_overrides(overrides);
+
+    let currency = s.data().currency;
+    s.call_on_name::<LinearLayout, _, _>(PROGRESS_VIEWER, |collection_viewer| {
+        if let Some(progress) = collection_viewer
+            .get_child_mut(position)
+            .and_then(|child| child.downcast_mut::<ProgressBar>())
+        {
+            progress.set_max(usize::from(overrides.target.unwrap_or(4)).max(4));
+            progress.set_label(progress_label(card, currency));
+        }
+    })
+    .expect(PROGRESS_VIEWER);
+
+    let format = s.data().format;
+    let name = CardName::from(card.card.name.clone());
+    background(
+        s.data().tx_error.clone(),
+        crate::overrides::set(format, name, overrides),
+    );
+}
+
+fn target_dialog() -> impl View {
+    Dialog::new()
+        .title("Copies to collect, empty to use the staples' number")
+        .content(
+            EditView::new()
+                .on_submit(|s, text| {
+                    let target = match text.trim() {
+                        "" => None,
+                        text => match text.parse::<u8>() {
+                            Ok(target) => Some(target),
+                            Err(_) => {
+                                information_dialog(s, "expected a number of copies", |_| {});
+                                return;
+                            }
+                        },
+                    };
+                    s.pop_layer();
+                    edit_overrides(s, |overrides| overrides.target = target);
+                })
+                .min_width(10),
+        )
+}
+
+pub fn collection_viewer(
+    collection: Rc<Checklist>,
+    sort_mode: SortMode,
+    currency: Currency,
+) -> impl View {
     let mut names = SelectView::new();
     let mut progress = LinearLayout::vertical();
     let max_text_width = collection
@@ -186,18 +545,13 @@ pub fn collection_viewer(collection: Rc<Checklist>, sort_mode: SortMode) -> impl
         }
     };
     for (index, card) in iter.enumerate() {
-        let metadata = card.metadata;
+        let target = card.overrides().target.unwrap_or(4);
         progress.add_child(
             ProgressBar::new()
                 .min(0)
-                .max(4)
-                .with_value(Counter::new(card.owned_versions().len()))
-                .with_label(move |value, _| {
-                    format!(
-                        "{value}/{} ({}%)",
-                        metadata.num_copies, metadata.percent_in_decks
-                    )
-                })
+                .max(usize::from(target).max(4))
+                .with_value(Counter::new(card.eligible_versions()))
+                .with_label(progress_label(card, currency))
                 .with_color(mtg_color_to_bar_color(card.card.colors.as_deref())),
         );
         let styled = SpannedString::styled(
@@ -216,7 +570,8 @@ pub fn collection_viewer(collection: Rc<Checklist>, sort_mode: SortMode) -> impl
                 let collection = collection.clone();
                 move |s, index| {
                     let card = &collection[*index];
-                    s.add_layer(edit_collected_card_dialog(card))
+                    let currency = s.data().currency;
+                    s.add_layer(edit_collected_card_dialog(card, currency))
                 }
             })
             .with_name(CARD_LIST),
@@ -231,6 +586,17 @@ pub fn collection_viewer(collection: Rc<Checklist>, sort_mode: SortMode) -> impl
     .on_pre_event('g', |s| {
         do_with_cardlist(s, |view| view.set_selection(0), |view| view.scroll_to_top())
     })
+    .on_pre_event('i', |s| {
+        edit_overrides(s, |overrides| overrides.ignored = !overrides.ignored)
+    })
+    .on_pre_event('p', |s| {
+        edit_overrides(s, |overrides| overrides.pinned = !overrides.pinned)
+    })
+    .on_pre_event('t', |s| s.add_layer(target_dialog().esq_to_quit()))
+    .on_pre_event('w', |s| {
+        let card = get_selected_card_name(s);
+        copies_dialog(s, card)
+    })
     // .on_pre_event_inner('c', |view, _| {
     //     1;
     //     Some(EventResult::Ignored)
@@ -0,0 +1,169 @@
+use cursive::{
+    view::{Nameable, Resizable, Scrollable},
+    views::{Dialog, HideableView, OnEventView, ScrollView, SelectView, TextView},
+    Cursive, View,
+};
+
+use crate::{
+    card_name::CardName,
+    collection::{journal::Change, Collection},
+    deckbuilder::{
+        allocation::Allocation,
+        library::{self, Library},
+    },
+};
+
+use super::{
+    background, collection_view::refresh_progress, save_as_dialog, vim::ViewExt, CursiveExt,
+};
+
+pub const DECK_PANEL: &str = "deck-panel";
+const DECK_LIST: &str = "deck-list";
+
+fn labels(library: &Library, allocation: &Allocation) -> Vec<(String, String)> {
+    library
+        .0
+        .iter()
+        .map(|(name, deck)| {
+            let ownership = allocation.ownership(name, deck);
+            let label = format!(
+                "{name} ({}{}) {:.0}%, {} missing",
+                deck.format,
+                if deck.built { ", built" } else { "" },
+                ownership.percent(),
+                ownership.missing_copies()
+            );
+            (label, name.clone())
+        })
+        .collect()
+}
+
+/// The saved decks with how much of each is owned, hidden until toggled.
+pub fn deck_panel(library: &Library, collection: &Collection) -> impl View {
+    let allocation = Allocation::new(library, collection);
+    let mut decks = SelectView::new().with_all(labels(library, &allocation));
+    decks.set_on_submit(|s, name: &String| deck_dialog(s, name));
+    HideableView::new(
+        Dialog::new().title("Decks").content(
+            OnEventView::new(decks.with_name(DECK_LIST))
+                .on_pre_event('b', toggle_built)
+                .scrollable()
+                .with_vim_keys()
+                .min_width(30),
+        ),
+    )
+    .hidden()
+    .with_name(DECK_PANEL)
+}
+
+pub fn toggle_deck_panel(s: &mut Cursive) {
+    s.call_on_name::<HideableView<Dialog>, _, _>(DECK_PANEL, |panel| {
+        panel.set_visible(!panel.is_visible())
+    })
+    .expect(DECK_PANEL);
+}
+
+/// Mirrors edits of the collection in the copy the decks are checked against and updates the
+/// decks' numbers and the copies the checklist counts.
+pub fn collection_changed(s: &mut Cursive, changes: &[Change]) {
+    let data = s.data();
+    for change in changes {
+        data.owned.apply(change);
+    }
+    refresh(s);
+}
+
+fn refresh(s: &mut Cursive) {
+    let data = s.data();
+    let allocation = Allocation::new(&data.library, &data.owned);
+    data.collection.set_built_decks(&allocation);
+    let labels = labels(&data.library, &allocation);
+    s.call_on_name::<SelectView<String>, _, _>(DECK_LIST, |decks| {
+        let selected = decks.selected_id();
+        decks.clear();
+        decks.add_all(labels);
+        if let Some(selected) = selected {
+            decks.set_selection(selected);
+        }
+    })
+    .expect(DECK_LIST);
+    refresh_progress(s);
+}
+
+/// Marks the selected deck as built, or as taken apart if it was, and saves it in the
+/// background.
+fn toggle_built(s: &mut Cursive) {
+    let Some(name) = s
+        .call_on_name::<SelectView<String>, _, _>(DECK_LIST, |decks| decks.selection())
+        .expect(DECK_LIST)
+    else {
+        return;
+    };
+    let name = name.to_string();
+    let data = s.data();
+    let Some(deck) = data.library.0.get_mut(&name) else {
+        return;
+    };
+    deck.built = !deck.built;
+    let built = deck.built;
+    background(data.tx_error.clone(), async move {
+        library::set_built(name, built).await?;
+        Ok(())
+    });
+    refresh(s);
+}
+
+/// What's missing from one deck, with a button to save it as a wishlist.
+fn deck_dialog(s: &mut Cursive, name: &str) {
+    let data = s.data();
+    let Some(deck) = data.library.0.get(name) else {
+        return;
+    };
+    let ownership = Allocation::new(&data.library, &data.owned).ownership(name, deck);
+    let mut text = format!(
+        "{}/{} copies owned, from {}\n",
+        ownership.owned, ownership.total, deck.source
+    );
+    if !ownership.missing.is_empty() {
+        text += "\nMissing:\n";
+        for (card, count) in &ownership.missing {
+            text += &format!("{count} {card}\n");
+        }
+    }
+    let missing = ownership
+        .missing
+        .into_iter()
+        .map(|(card, count)| (usize::from(count), card))
+        .collect::<Vec<_>>();
+    let title = format!("{name} | {}", deck.format);
+    s.add_layer(
+        Dialog::new()
+            .title(title)
+            .content(ScrollView::new(TextView::new(text)))
+            .button("Save missing as", move |s| {
+                s.add_layer(save_as_dialog(missing.clone()).esq_to_quit())
+            })
+            .dismiss_button("Done")
+            .esq_to_quit(),
+    );
+}
+
+/// Every owned copy of the card and the built deck it's in.
+pub fn copies_dialog(s: &mut Cursive, card: CardName) {
+    let data = s.data();
+    let allocation = Allocation::new(&data.library, &data.owned);
+    let mut text = String::new();
+    for (version, deck) in allocation.copies(&card) {
+        text += &format!("{version}: {}\n", deck.unwrap_or("free"));
+    }
+    if text.is_empty() {
+        text = "No copies owned".into();
+    }
+    s.add_layer(
+        Dialog::new()
+            .title(format!("Where is my {card}"))
+            .content(ScrollView::new(TextView::new(text)))
+            .dismiss_button("Done")
+            .esq_to_quit(),
+    );
+}
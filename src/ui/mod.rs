@@ -1,27 +1,38 @@
 mod collection_view;
+mod decks;
 pub mod panic;
+mod plan;
 mod show;
 mod stats;
+mod trends;
 mod vim;
 
-use std::{fmt::Write, rc::Rc};
+use std::{collections::BTreeMap, fmt::Write, rc::Rc};
 
 use cursive::{
     backends::crossterm,
-    theme::{BaseColor, Color},
+    event::Event,
+    theme::Color,
     view::{Nameable, Resizable},
-    views::{Dialog, EditView, TextView},
+    views::{Dialog, EditView, LinearLayout, TextView},
     Cursive, View,
 };
-use itertools::Itertools;
 use scryfall::format::Format;
 use std::future::Future;
 use tokio::sync::mpsc::{self, error::TryRecvError, UnboundedSender};
 
-use crate::checklist::Checklist;
+use crate::{
+    checklist::Checklist,
+    collection::{journal::Change, Collection},
+    deckbuilder::library::Library,
+    prices::Currency,
+    staples::{snapshots::Trends, Board},
+};
 
 use self::{
-    collection_view::{collection_viewer, CardList, SortMode, CARD_LIST},
+    collection_view::{apply_changes, collection_viewer, CardList, SortMode, CARD_LIST},
+    decks::{deck_panel, toggle_deck_panel},
+    plan::{show_plan, Planned},
     vim::ViewExt,
 };
 
@@ -37,7 +48,7 @@ impl CursiveExt for Cursive {
 
 fn background<F>(tx_error: UnboundedSender<anyhow::Error>, task: F)
 where
-    F: Future<Output = anyhow::Result<()>> + Send + Sync + 'static,
+    F: Future<Output = anyhow::Result<()>> + Send + 'static,
 {
     tokio::spawn(async move {
         if let Err(e) = task.await {
@@ -80,13 +91,15 @@ where
 }
 
 const MAIN_LAYOUT: &str = "main-layout";
+const CHECKLIST_LAYOUT: &str = "checklist-layout";
 
-fn save_as_dialog(missing: Vec<(usize, String, f32)>) -> impl View {
+/// Asks for a file name and writes the cards to it as a `N Card Name` list.
+fn save_as_dialog(cards: Vec<(usize, String)>) -> impl View {
     Dialog::new().title("Save as").content(
         EditView::new()
             .on_submit(move |s, file_name| {
                 let mut buf = String::new();
-                for (count, name, _) in &missing {
+                for (count, name) in &cards {
                     writeln!(buf, "{count} {name}").unwrap();
                 }
                 if let Err(e) = std::fs::write(file_name, buf.as_bytes()) {
@@ -103,82 +116,166 @@ fn save_as_dialog(missing: Vec<(usize, String, f32)>) -> impl View {
     )
 }
 
+/// Runs an undo or redo in the background, the changes it made are picked up by the main loop.
+fn undo_or_redo<F>(s: &mut Cursive, task: F)
+where
+    F: Future<Output = anyhow::Result<Option<Vec<Change>>>> + Send + Sync + 'static,
+{
+    // the edit dialogs don't know how to refresh themselves
+    if s.screen().len() > 1 {
+        return;
+    }
+    let tx_changes = s.data().tx_changes.clone();
+    background(s.data().tx_error.clone(), async move {
+        let _ = tx_changes.send(task.await?);
+        Ok(())
+    });
+}
+
 struct Data {
     pub tx_error: UnboundedSender<anyhow::Error>,
+    pub tx_changes: UnboundedSender<Option<Vec<Change>>>,
+    pub tx_plan: UnboundedSender<Planned>,
     pub collection: Rc<Checklist>,
+    /// Which board the wishlist and statistics look at.
+    pub board: Board,
+    pub currency: Currency,
+    pub format: Format,
+    /// The whole collection, not just this format's staples, which the decks are checked against.
+    pub owned: Collection,
+    pub library: Library,
 }
 
-pub fn ui(collection: Checklist, format: Format) {
+fn title(format: Format, board: Board) -> String {
+    match board {
+        Board::Any => format!("Lord Xander, The Collector | {format}"),
+        board => format!("Lord Xander, The Collector | {format} | {board}"),
+    }
+}
+
+/// Parses a theme color, `default` being the terminal's own color.
+fn parse_color(color: &str) -> Option<Color> {
+    match color {
+        "default" => Some(Color::TerminalDefault),
+        color => Color::parse(color),
+    }
+}
+
+pub fn ui(
+    collection: Checklist,
+    format: Format,
+    trends: Option<Trends>,
+    currency: Currency,
+    theme: BTreeMap<String, String>,
+    library: Library,
+    owned: Collection,
+) {
     let mut cursive = Cursive::new();
     let (tx_error, mut rx_error) = mpsc::unbounded_channel::<anyhow::Error>();
+    let (tx_changes, mut rx_changes) = mpsc::unbounded_channel::<Option<Vec<Change>>>();
+    let (tx_plan, mut rx_plan) = mpsc::unbounded_channel::<Planned>();
     cursive.with_theme(|current| {
         use cursive::theme::PaletteColor;
-        current.palette[PaletteColor::Background] = Color::TerminalDefault;
-        current.palette[PaletteColor::HighlightInactive] = Color::Dark(BaseColor::White);
-        current.palette[PaletteColor::HighlightText] = Color::Dark(BaseColor::Black);
-        current.palette[PaletteColor::Highlight] = Color::TerminalDefault;
-        current.palette[PaletteColor::Primary] = Color::TerminalDefault;
-        current.palette[PaletteColor::Secondary] = Color::TerminalDefault;
-        current.palette[PaletteColor::Shadow] = Color::TerminalDefault;
-        current.palette[PaletteColor::Tertiary] = Color::TerminalDefault;
-        current.palette[PaletteColor::View] = Color::TerminalDefault;
-        current.palette[PaletteColor::TitlePrimary] = Color::Dark(BaseColor::Blue);
-        current.palette[PaletteColor::TitleSecondary] = Color::TerminalDefault;
+        for (key, color) in &theme {
+            match (key.parse::<PaletteColor>(), parse_color(color)) {
+                (Ok(key), Some(color)) => current.palette[key] = color,
+                (Err(_), _) => println!("[WARN] unknown palette color {key:?} in the theme"),
+                (_, None) => println!("[WARN] invalid color {color:?} for {key} in the theme"),
+            }
+        }
     });
 
     let collection = Rc::new(collection);
+    let decks = deck_panel(&library, &owned);
     cursive.set_user_data(Data {
         tx_error,
+        tx_changes,
+        tx_plan,
         collection: collection.clone(),
+        board: Board::Any,
+        currency,
+        format,
+        owned,
+        library,
     });
 
     let sort_mode = std::cell::Cell::new(SortMode::Collection);
 
     cursive.add_layer(
         Dialog::new()
-            .title(format!("Lord Xander, The Collector | {format}"))
-            .content(collection_viewer(collection.clone(), sort_mode.get()))
+            .title(title(format, Board::Any))
+            .content(
+                LinearLayout::horizontal()
+                    .child(collection_viewer(
+                        collection.clone(),
+                        sort_mode.get(),
+                        currency,
+                    ))
+                    .child(decks)
+                    .with_name(CHECKLIST_LAYOUT),
+            )
             .button("To Wishlist", |s| {
-                let collection = s.data().collection.clone();
-                let missing = s
-                    .call_on_name::<CardList, _, _>(CARD_LIST, |view| {
-                        view.iter()
-                            .map(|(_, index)| {
-                                let card = &collection[*index];
-                                (
-                                    (card.metadata.num_copies as usize)
-                                        .saturating_sub(card.owned_versions().len()),
-                                    card.card.name.clone(),
-                                    card.metadata.percent_in_decks,
-                                )
-                            })
-                            .filter(|(missing, _, _)| *missing > 0)
-                            .sorted_unstable_by(|(_, _, percent_a), (_, _, percent_b)| {
-                                percent_a.total_cmp(percent_b).reverse()
-                            })
-                            .collect::<Vec<_>>()
-                    })
-                    .unwrap();
+                let Data {
+                    collection,
+                    board,
+                    currency,
+                    ..
+                } = s.data();
+                let missing = collection
+                    .wishlist(*board, *currency)
+                    .into_iter()
+                    .map(|wanted| (wanted.missing, wanted.name))
+                    .collect::<Vec<_>>();
                 s.add_layer(save_as_dialog(missing).esq_to_quit())
             })
+            .button("Plan Purchases", |s| {
+                s.add_layer(plan::plan_dialog().esq_to_quit())
+            })
             .button("Show Stattistics", |s| {
-                let stats_view = stats::stats(&s.data().collection);
+                let Data {
+                    collection,
+                    board,
+                    currency,
+                    ..
+                } = s.data();
+                let stats_view = stats::stats(collection, *board, *currency);
                 s.add_layer(stats_view.esq_to_quit())
             })
+            .button("Trends", move |s| {
+                s.add_layer(trends::trends(trends.as_ref()).esq_to_quit())
+            })
+            .button("Decks", toggle_deck_panel)
+            .button("Toggle Board", move |s| {
+                let data = s.data();
+                data.board = data.board.next();
+                let board = data.board;
+                s.call_on_name::<Dialog, _, _>("collection-viewer", |dialog| {
+                    dialog.set_title(title(format, board));
+                });
+            })
             .button("Toggle Sort", move |s| {
                 sort_mode.set(match sort_mode.get() {
                     SortMode::Collection => SortMode::NoCollection,
                     SortMode::NoCollection => SortMode::Collection,
                 });
-                s.call_on_name::<Dialog, _, _>("collection-viewer", |dialog| {
-                    dialog.set_content(collection_viewer(collection.clone(), sort_mode.get()));
+                s.call_on_name::<LinearLayout, _, _>(CHECKLIST_LAYOUT, |layout| {
+                    layout.remove_child(0);
+                    layout.insert_child(
+                        0,
+                        collection_viewer(collection.clone(), sort_mode.get(), currency),
+                    );
                 });
             })
             .with_name("collection-viewer"),
     );
 
     cursive.set_on_post_event('q', |s| s.quit());
+    cursive.add_global_callback('u', |s| undo_or_redo(s, crate::collection::undo()));
+    cursive.add_global_callback(Event::CtrlChar('r'), |s| {
+        undo_or_redo(s, crate::collection::redo())
+    });
 
+    crate::cache::set_quiet(true);
     let mut runner = cursive.runner(crossterm::Backend::init().unwrap());
     runner.refresh();
     while runner.is_running() {
@@ -192,5 +289,28 @@ pub fn ui(collection: Checklist, format: Format) {
             Err(TryRecvError::Empty) => {}
             Err(TryRecvError::Disconnected) => unreachable!(),
         }
+        match rx_changes.try_recv() {
+            Ok(None) => information_dialog(&mut runner, "Nothing to undo or redo", |_| {}),
+            Ok(Some(changes)) if changes.is_empty() => information_dialog(
+                &mut runner,
+                "Nothing changed, the collection was already like that",
+                |_| {},
+            ),
+            Ok(Some(changes)) => {
+                apply_changes(&mut runner, &changes);
+                runner.refresh();
+            }
+            Err(TryRecvError::Empty) => {}
+            Err(TryRecvError::Disconnected) => unreachable!(),
+        }
+        match rx_plan.try_recv() {
+            Ok(planned) => {
+                show_plan(&mut runner, planned);
+                runner.refresh();
+            }
+            Err(TryRecvError::Empty) => {}
+            Err(TryRecvError::Disconnected) => unreachable!(),
+        }
     }
+    crate::cache::set_quiet(false);
 }
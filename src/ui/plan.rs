@@ -0,0 +1,167 @@
+use cursive::{
+    view::{Nameable, Resizable},
+    views::{Dialog, EditView, ListView, ScrollView, SelectView, TextView},
+    Cursive, View,
+};
+
+use crate::{
+    card_name::CardName,
+    deckbuilder::allocation::Allocation,
+    planner::{self, Goal, Plan},
+};
+
+use super::{background, save_as_dialog, vim::ViewExt, CursiveExt, Data};
+
+const BUDGET_EDITOR: &str = "plan-budget";
+const GOAL_PICKER: &str = "plan-goal";
+const DECKS_EDITOR: &str = "plan-decks";
+
+/// A finished plan, sent back to the ui when it was made in the background.
+pub struct Planned {
+    goal: Goal,
+    plan: Plan,
+    budget: f32,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum GoalChoice {
+    Top(usize),
+    Colors,
+    Decks,
+}
+
+pub fn plan_dialog() -> impl View {
+    Dialog::new()
+        .title("Plan Purchases")
+        .content(
+            ListView::new()
+                .child(
+                    "Budget",
+                    EditView::new().with_name(BUDGET_EDITOR).min_width(8),
+                )
+                .child(
+                    "Optimize for",
+                    SelectView::new()
+                        .popup()
+                        .item("Top 20 staples", GoalChoice::Top(20))
+                        .item("Top 50 staples", GoalChoice::Top(50))
+                        .item("Top 150 staples", GoalChoice::Top(150))
+                        .item("Color coverage", GoalChoice::Colors)
+                        .item("Decklists", GoalChoice::Decks)
+                        .with_name(GOAL_PICKER),
+                )
+                .child(
+                    "Decklist files",
+                    EditView::new().with_name(DECKS_EDITOR).min_width(30),
+                ),
+        )
+        .button("Plan", |s| {
+            if let Err(e) = plan(s) {
+                let _ = s.data().tx_error.send(e);
+            }
+        })
+}
+
+fn read_decks(paths: &str) -> anyhow::Result<Vec<Vec<(CardName, u8)>>> {
+    paths
+        .split_whitespace()
+        .map(|path| {
+            let text = std::fs::read_to_string(path)
+                .map_err(|e| anyhow::anyhow!("reading {path}: {e}"))?;
+            Ok(crate::deckbuilder::parse_deck(&text)?
+                .into_iter()
+                .map(|card| (CardName::from(card.name), card.count))
+                .collect())
+        })
+        .collect()
+}
+
+fn plan(s: &mut Cursive) -> anyhow::Result<()> {
+    let budget = s
+        .call_on_name::<EditView, _, _>(BUDGET_EDITOR, |view| view.get_content())
+        .expect(BUDGET_EDITOR);
+    let Ok(budget) = budget.trim().parse::<f32>() else {
+        anyhow::bail!("the budget must be a number, got {:?}", budget.trim());
+    };
+    let choice = s
+        .call_on_name::<SelectView<GoalChoice>, _, _>(GOAL_PICKER, |view| view.selection())
+        .expect(GOAL_PICKER)
+        .map(|c| *c)
+        .unwrap_or(GoalChoice::Top(50));
+
+    let collection = s.data().collection.clone();
+    let Data {
+        board, currency, ..
+    } = *s.data();
+    let mut candidates = planner::candidates(&collection, board, currency);
+    let goal = match choice {
+        GoalChoice::Top(n) => Goal::Top(n),
+        GoalChoice::Colors => Goal::Colors,
+        GoalChoice::Decks => {
+            let paths = s
+                .call_on_name::<EditView, _, _>(DECKS_EDITOR, |view| view.get_content())
+                .expect(DECKS_EDITOR);
+            let decks = read_decks(&paths)?;
+            if decks.is_empty() {
+                anyhow::bail!("no decklist files given");
+            }
+            // the deck's cards may have to be downloaded, which would freeze the ui
+            let tx_plan = s.data().tx_plan.clone();
+            background(s.data().tx_error.clone(), async move {
+                let owned = crate::collection::load().await?;
+                let library = crate::deckbuilder::library::load().await?;
+                let free = Allocation::new(&library, &owned).free;
+                planner::add_deck_cards(&mut candidates, &decks, &free, currency).await?;
+                let goal = Goal::Decks(decks);
+                let plan = planner::plan(&candidates, &goal, budget);
+                let _ = tx_plan.send(Planned { goal, plan, budget });
+                Ok(())
+            });
+            s.pop_layer();
+            return Ok(());
+        }
+    };
+
+    let plan = planner::plan(&candidates, &goal, budget);
+    s.pop_layer();
+    show_plan(s, Planned { goal, plan, budget });
+    Ok(())
+}
+
+pub fn show_plan(s: &mut Cursive, Planned { goal, plan, budget }: Planned) {
+    let currency = s.data().currency;
+    let mut text = String::new();
+    for purchase in &plan.purchases {
+        text += &format!(
+            "{} {} ({} each)\n",
+            purchase.copies,
+            purchase.name,
+            currency.display(purchase.price)
+        );
+    }
+    if !plan.unpriced.is_empty() {
+        text += "\nNo known price, left out:\n";
+        for name in &plan.unpriced {
+            text += &format!("{name}\n");
+        }
+    }
+    let lines = plan
+        .purchases
+        .iter()
+        .map(|purchase| (purchase.copies, purchase.name.to_string()))
+        .collect::<Vec<_>>();
+
+    s.add_layer(
+        Dialog::new()
+            .title(format!(
+                "{goal} | {} of {}",
+                currency.display(plan.spent),
+                currency.display(budget)
+            ))
+            .content(ScrollView::new(TextView::new(text)))
+            .button("Save as", move |s| {
+                s.add_layer(save_as_dialog(lines.clone()).esq_to_quit())
+            })
+            .esq_to_quit(),
+    );
+}
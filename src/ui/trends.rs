@@ -0,0 +1,38 @@
+use cursive::{
+    theme::Effect,
+    view::Margins,
+    views::{Dialog, LinearLayout, PaddedView, TextView},
+    View,
+};
+
+use crate::staples::snapshots::{Trend, Trends};
+
+const SHOWN: usize = 20;
+
+fn column<'t>(title: &str, trends: impl Iterator<Item = &'t Trend>) -> impl View {
+    let mut text = String::new();
+    for trend in trends.take(SHOWN) {
+        text += &format!("{trend}\n");
+    }
+    PaddedView::new(
+        Margins::lrtb(1, 1, 1, 1),
+        LinearLayout::vertical()
+            .child(TextView::new(title).style(Effect::Bold))
+            .child(TextView::new(text)),
+    )
+}
+
+pub fn trends(trends: Option<&Trends>) -> impl View {
+    match trends {
+        Some(trends) => Dialog::new()
+            .title(format!("Trends | {} -> {}", trends.since, trends.until))
+            .content(
+                LinearLayout::horizontal()
+                    .child(column("Rising", trends.rising()))
+                    .child(column("Falling", trends.falling())),
+            ),
+        None => Dialog::new().title("Trends").content(TextView::new(
+            "Nothing to compare yet, every run saves a snapshot of the format",
+        )),
+    }
+}